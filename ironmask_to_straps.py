@@ -1,3 +1,6 @@
+from straps._straps_ext import PyCompGraph
+
+
 def IronMasktoStraps(lines, d):
     #Take input a script implementable in IronMask and convert it to a Straps script
     #Assumes operations are either + or * or =
@@ -22,3 +25,20 @@ def IronMasktoStraps(lines, d):
                     elif len(tokens)==3: #"="
                         c.assign(dest, tokens[2])
     return c
+
+
+def IronMasktoPyCompGraph(lines, d=None):
+    """Convert an IronMask gadget description to a PyCompGraph.
+
+    `lines` is an iterable of lines of the IronMask script (or the whole script
+    as a single string). If `d` is given, it must match the #SHARES directive.
+    Parsing is done by PyCompGraph.from_ironmask, which supports #SHARES, #IN,
+    #OUT, #RANDOMS, and the +, *, = and ! operations.
+    """
+    text = lines if isinstance(lines, str) else "\n".join(l.rstrip("\n") for l in lines)
+    c = PyCompGraph.from_ironmask(text)
+    if d is not None and c.n_shares() != d:
+        raise ValueError(
+            "Gadget has {} shares, expected {}.".format(c.n_shares(), d)
+        )
+    return c
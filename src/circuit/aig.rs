@@ -0,0 +1,384 @@
+// STRAPS - Statistical Testing of RAndom Probing Security
+// Copyright (C) 2021 UCLouvain
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! And-Inverter Graphs, used as intermediate representation for netlist imports.
+//!
+//! Conversion to a [`SlSharedCircuit`] recovers XOR gates: the AIG pattern
+//! `!(x & y) & !(!x & !y)` becomes a single `VarSrc::Sum`.
+
+use super::import_sl_sc::new_sl_sc;
+use super::port_spec::PortSpec;
+use super::sl_sc::{SlSharedCircuit, Var, VarSrc};
+use super::var_set::VarIdx;
+use std::collections::{HashMap, HashSet};
+
+/// AIG literal: `2*node + negated`.
+pub(crate) type Lit = usize;
+
+pub(crate) const LIT_FALSE: Lit = 0;
+pub(crate) const LIT_TRUE: Lit = 1;
+
+fn lit_node(lit: Lit) -> usize {
+    lit >> 1
+}
+fn lit_neg(lit: Lit) -> bool {
+    lit & 1 == 1
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum AigNode {
+    False,
+    Input(String),
+    And(Lit, Lit),
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Aig {
+    /// Node 0 is the constant false.
+    nodes: Vec<AigNode>,
+    /// Optional signal names of literals.
+    names: HashMap<Lit, String>,
+    outputs: HashMap<String, Lit>,
+}
+
+/// Gate of the recovered circuit: XOR(x, y) gates have literal operands.
+#[derive(Debug, Clone, Copy)]
+enum Gate {
+    And(Lit, Lit),
+    Xor(Lit, Lit),
+}
+
+impl Aig {
+    pub(crate) fn new() -> Self {
+        Self {
+            nodes: vec![AigNode::False],
+            names: HashMap::new(),
+            outputs: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn add_input(&mut self, name: String) -> Lit {
+        self.names.insert(2 * self.nodes.len(), name.clone());
+        self.nodes.push(AigNode::Input(name));
+        2 * (self.nodes.len() - 1)
+    }
+
+    pub(crate) fn add_and(&mut self, a: Lit, b: Lit) -> Lit {
+        self.nodes.push(AigNode::And(a, b));
+        2 * (self.nodes.len() - 1)
+    }
+
+    pub(crate) fn and_many(&mut self, lits: impl IntoIterator<Item = Lit>) -> Lit {
+        let mut lits = lits.into_iter();
+        match lits.next() {
+            None => LIT_TRUE,
+            Some(first) => lits.fold(first, |acc, l| self.add_and(acc, l)),
+        }
+    }
+
+    pub(crate) fn or_many(&mut self, lits: impl IntoIterator<Item = Lit>) -> Lit {
+        let lits = lits.into_iter().map(|l| l ^ 1).collect::<Vec<_>>();
+        self.and_many(lits) ^ 1
+    }
+
+    /// XOR as `!(a & b) & !(!a & !b)`, which is recovered as a single gate.
+    pub(crate) fn add_xor(&mut self, a: Lit, b: Lit) -> Lit {
+        let a_and_b = self.add_and(a, b);
+        let na_and_nb = self.add_and(a ^ 1, b ^ 1);
+        self.add_and(a_and_b ^ 1, na_and_nb ^ 1)
+    }
+
+    pub(crate) fn set_name(&mut self, lit: Lit, name: String) {
+        self.names.entry(lit).or_insert(name);
+    }
+
+    pub(crate) fn set_output(&mut self, name: String, lit: Lit) {
+        self.outputs.insert(name, lit);
+    }
+
+    /// Gate computed by each node, recognizing XOR patterns.
+    fn gates(&self) -> Vec<Option<Gate>> {
+        let and_ops = |lit: Lit| match self.nodes[lit_node(lit)] {
+            AigNode::And(a, b) if lit_neg(lit) => Some((a, b)),
+            _ => None,
+        };
+        self.nodes
+            .iter()
+            .map(|node| match *node {
+                AigNode::And(l1, l2) => Some(match (and_ops(l1), and_ops(l2)) {
+                    // !(a & b) & !(!a & !b) = a ^ b
+                    (Some((a, b)), Some((c, d)))
+                        if (c, d) == (a ^ 1, b ^ 1) || (c, d) == (b ^ 1, a ^ 1) =>
+                    {
+                        Gate::Xor(a, b)
+                    }
+                    _ => Gate::And(l1, l2),
+                }),
+                AigNode::False | AigNode::Input(_) => None,
+            })
+            .collect()
+    }
+
+    /// Build a circuit, the roles of the inputs and outputs are given by `spec`.
+    pub(crate) fn to_circuit(&self, spec: &PortSpec) -> Result<SlSharedCircuit, String> {
+        spec.check()?;
+        let gates = self.gates();
+        let mut builder = CircuitBuilder {
+            aig: self,
+            vars: Vec::new(),
+            used_names: HashSet::new(),
+            node_vars: vec![None; self.nodes.len()],
+            not_vars: HashMap::new(),
+            const_vars: [None; 2],
+        };
+        // All inputs are created first, in port order.
+        let mut input_nodes = HashMap::new();
+        for (i, node) in self.nodes.iter().enumerate() {
+            if let AigNode::Input(name) = node {
+                if input_nodes.insert(name.as_str(), i).is_some() {
+                    return Err(format!("duplicate input name '{}' in the netlist", name));
+                }
+                spec.input_role(name)?;
+            }
+        }
+        let inputs = spec
+            .inputs
+            .iter()
+            .enumerate()
+            .flat_map(|(port, sharing)| {
+                sharing
+                    .iter()
+                    .enumerate()
+                    .map(move |(share, name)| (name, VarSrc::Input(port, share)))
+            })
+            .chain(spec.randoms.iter().map(|name| (name, VarSrc::Random)));
+        for (name, src) in inputs {
+            let node = *input_nodes
+                .get(name.as_str())
+                .ok_or_else(|| format!("no input named '{}' in the netlist", name))?;
+            let var = builder.new_var(src, name);
+            builder.node_vars[node] = Some((var, false));
+        }
+        // Then gates, in dependency order.
+        let mut outputs = Vec::new();
+        for (port, sharing) in spec.outputs.iter().enumerate() {
+            for (share, name) in sharing.iter().enumerate() {
+                let lit = *self
+                    .outputs
+                    .get(name)
+                    .ok_or_else(|| format!("no output named '{}' in the netlist", name))?;
+                outputs.push(((port, share), name, lit));
+            }
+        }
+        for node in dfs_order(&gates, outputs.iter().map(|(_, _, lit)| lit_node(*lit))) {
+            builder.emit_node(node, gates[node].unwrap())?;
+        }
+        for (port_share, name, lit) in outputs {
+            let var = builder.lit_var(lit)?;
+            if builder.vars[var].output_port.is_some() {
+                return Err(format!("output '{}' has the value of another output", name));
+            }
+            builder.vars[var].output_port = Some(port_share);
+        }
+        new_sl_sc(
+            builder.vars,
+            spec.n_shares,
+            spec.inputs.len(),
+            spec.outputs.len(),
+        )
+    }
+}
+
+/// Gates reachable from `roots`, in post-order.
+fn dfs_order(gates: &[Option<Gate>], roots: impl Iterator<Item = usize>) -> Vec<usize> {
+    let mut res = Vec::new();
+    let mut seen = vec![false; gates.len()];
+    // (node, children done)
+    let mut stack = roots.map(|n| (n, false)).collect::<Vec<_>>();
+    stack.reverse();
+    while let Some((node, done)) = stack.pop() {
+        match (gates[node], done) {
+            (Some(_), true) => res.push(node),
+            (Some(Gate::And(a, b)), false) | (Some(Gate::Xor(a, b)), false) if !seen[node] => {
+                seen[node] = true;
+                stack.push((node, true));
+                stack.push((lit_node(b), false));
+                stack.push((lit_node(a), false));
+            }
+            _ => {}
+        }
+    }
+    res
+}
+
+struct CircuitBuilder<'a> {
+    aig: &'a Aig,
+    vars: Vec<Var>,
+    used_names: HashSet<String>,
+    /// Variable v and inversion i for each node (node value is v ^ i).
+    node_vars: Vec<Option<(VarIdx, bool)>>,
+    not_vars: HashMap<VarIdx, VarIdx>,
+    /// Variables of the constants false and true, created when used.
+    const_vars: [Option<VarIdx>; 2],
+}
+
+impl<'a> CircuitBuilder<'a> {
+    fn new_var(&mut self, src: VarSrc, name: &str) -> VarIdx {
+        let mut var_name = name.to_owned();
+        let mut k = 1;
+        while !self.used_names.insert(var_name.clone()) {
+            var_name = format!("{}.{}", name, k);
+            k += 1;
+        }
+        self.vars.push(Var {
+            src,
+            output_port: None,
+            name: var_name,
+        });
+        self.vars.len() - 1
+    }
+
+    fn lit_name(&self, lit: Lit) -> String {
+        match self.aig.names.get(&lit) {
+            Some(name) => name.clone(),
+            None if lit_neg(lit) => format!("~{}", self.lit_name(lit ^ 1)),
+            None => format!("n{}", lit_node(lit)),
+        }
+    }
+
+    fn const_var(&mut self, value: bool) -> VarIdx {
+        match self.const_vars[value as usize] {
+            Some(var) => var,
+            None => {
+                let var = self.new_var(VarSrc::Const(value), &format!("const{}", value as u8));
+                self.const_vars[value as usize] = Some(var);
+                var
+            }
+        }
+    }
+
+    /// Variable and inversion of an already emitted node.
+    fn node_var(&mut self, node: usize) -> Result<(VarIdx, bool), String> {
+        match self.aig.nodes[node] {
+            AigNode::False => Ok((self.const_var(false), false)),
+            _ => self.node_vars[node].ok_or_else(|| "combinational loop".to_owned()),
+        }
+    }
+
+    fn lit_var(&mut self, lit: Lit) -> Result<VarIdx, String> {
+        if lit_node(lit) == lit_node(LIT_FALSE) {
+            return Ok(self.const_var(lit_neg(lit)));
+        }
+        let (var, inv) = self.node_var(lit_node(lit))?;
+        if inv == lit_neg(lit) {
+            return Ok(var);
+        }
+        if let Some(not_var) = self.not_vars.get(&var) {
+            return Ok(*not_var);
+        }
+        let name = self.lit_name(lit);
+        let not_var = self.new_var(VarSrc::Not([var]), &name);
+        self.not_vars.insert(var, not_var);
+        Ok(not_var)
+    }
+
+    fn emit_node(&mut self, node: usize, gate: Gate) -> Result<(), String> {
+        let res = match gate {
+            Gate::And(a, b) => {
+                let mut ops = vec![self.lit_var(a)?, self.lit_var(b)?];
+                ops.sort_unstable();
+                let name = self.lit_name(2 * node);
+                (self.new_var(VarSrc::Product(ops), &name), false)
+            }
+            Gate::Xor(a, b) => {
+                // Inversions of the operands are moved after the XOR.
+                let (va, ia) = self.node_var(lit_node(a))?;
+                let (vb, ib) = self.node_var(lit_node(b))?;
+                let inv = ia ^ ib ^ lit_neg(a) ^ lit_neg(b);
+                let mut ops = vec![va, vb];
+                ops.sort_unstable();
+                let name = self.lit_name(2 * node + inv as usize);
+                (self.new_var(VarSrc::Sum(ops), &name), inv)
+            }
+        };
+        self.node_vars[node] = Some(res);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xor_recovery() {
+        let mut aig = Aig::new();
+        let a = aig.add_input("a".to_owned());
+        let b = aig.add_input("b".to_owned());
+        // a ^ b as a | b & !(a & b), xnor as (a & b) | (!a & !b)
+        let a_and_b = aig.add_and(a, b);
+        let na_and_nb = aig.add_and(a ^ 1, b ^ 1);
+        let xor = aig.add_and(a_and_b ^ 1, na_and_nb ^ 1);
+        aig.set_output("x".to_owned(), xor);
+        aig.set_output("y".to_owned(), xor ^ 1);
+        let spec = PortSpec {
+            n_shares: 1,
+            inputs: vec![vec!["a".to_owned()], vec!["b".to_owned()]],
+            outputs: vec![vec!["x".to_owned()], vec!["y".to_owned()]],
+            randoms: vec![],
+        };
+        let circ = aig.to_circuit(&spec).unwrap();
+        assert_eq!(circ.vars.len(), 4);
+        assert_eq!(circ.vars[2].src, VarSrc::Sum(vec![0, 1]));
+        assert_eq!(circ.vars[3].src, VarSrc::Not([2]));
+        assert_eq!(circ.vars[3].output_port, Some((1, 0)));
+    }
+
+    #[test]
+    fn test_const() {
+        let mut aig = Aig::new();
+        let a = aig.add_input("a".to_owned());
+        let x = aig.add_and(a, LIT_TRUE);
+        aig.set_output("x".to_owned(), x);
+        aig.set_output("y".to_owned(), LIT_FALSE);
+        let spec = PortSpec {
+            n_shares: 1,
+            inputs: vec![vec!["a".to_owned()]],
+            outputs: vec![vec!["x".to_owned()], vec!["y".to_owned()]],
+            randoms: vec![],
+        };
+        let circ = aig.to_circuit(&spec).unwrap();
+        assert!(circ
+            .vars
+            .iter()
+            .any(|v| v.src == VarSrc::Const(false) && v.output_port == Some((1, 0))));
+    }
+    #[test]
+    fn test_duplicate_input() {
+        let mut aig = Aig::new();
+        let a = aig.add_input("a".to_owned());
+        let b = aig.add_input("a".to_owned());
+        let x = aig.add_and(a, b);
+        aig.set_output("x".to_owned(), x);
+        let spec = PortSpec {
+            n_shares: 1,
+            inputs: vec![vec!["a".to_owned()]],
+            outputs: vec![vec!["x".to_owned()]],
+            randoms: vec![],
+        };
+        assert!(aig.to_circuit(&spec).is_err());
+    }
+}
@@ -0,0 +1,222 @@
+// STRAPS - Statistical Testing of RAndom Probing Security
+// Copyright (C) 2021 UCLouvain
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Exhaustive (reference) simulatability: slow, but exact.
+//!
+//! For every value of the input shares, the distribution of the probes is computed by
+//! enumerating all the values of the randoms (bitsliced, 64 at a time). The distribution depends
+//! on an input share iff flipping that share changes it for some value of the other shares.
+
+use super::sl_sc::{SlSharedCircuit, VarSrc};
+use super::var_set::VarIdx;
+use std::collections::HashMap;
+
+/// Maximum number of randoms for exhaustive simulation.
+const MAX_RANDOMS: usize = 32;
+/// Maximum number of input shares for exhaustive simulation.
+const MAX_INPUTS: usize = 20;
+
+/// Lane patterns of the first 6 enumerated variables in a 64-bit word.
+pub(super) const LANE_PATTERNS: [u64; 6] = [
+    0xAAAA_AAAA_AAAA_AAAA,
+    0xCCCC_CCCC_CCCC_CCCC,
+    0xF0F0_F0F0_F0F0_F0F0,
+    0xFF00_FF00_FF00_FF00,
+    0xFFFF_0000_FFFF_0000,
+    0xFFFF_FFFF_0000_0000,
+];
+
+/// Minimal set of input shares on which the joint distribution of the probes depends.
+///
+/// This enumerates all values of the input shares and of the randoms on which the probes depend,
+/// hence is only usable on small circuits.
+pub fn sim_set_exhaustive<T: IntoIterator<Item = u32>>(
+    circ: &SlSharedCircuit,
+    probes: T,
+) -> Result<Vec<u32>, String> {
+    let mut probes = probes.into_iter().map(|x| x as VarIdx).collect::<Vec<_>>();
+    probes.sort_unstable();
+    probes.dedup();
+    if probes.len() > 64 {
+        return Err("at most 64 probes are supported".to_owned());
+    }
+    if let Some(p) = probes.iter().find(|p| **p >= circ.vars.len()) {
+        return Err(format!("variable {} does not exist", p));
+    }
+    let cone = cone(circ, &probes);
+    // Public values are enumerated as the input shares, but are not reported.
+    let inputs = cone
+        .iter()
+        .copied()
+        .filter(|i| matches!(circ.vars[*i].src, VarSrc::Input(_, _) | VarSrc::Public(_)))
+        .collect::<Vec<_>>();
+    let randoms = cone
+        .iter()
+        .copied()
+        .filter(|i| circ.vars[*i].src == VarSrc::Random)
+        .collect::<Vec<_>>();
+    if inputs.len() > MAX_INPUTS || randoms.len() > MAX_RANDOMS {
+        return Err(format!(
+            "too many input shares and public values ({}) or randoms ({}) for exhaustive simulation",
+            inputs.len(),
+            randoms.len()
+        ));
+    }
+    let distrs = (0..(1u64 << inputs.len()))
+        .map(|x| probes_distribution(circ, &cone, &inputs, &randoms, &probes, x))
+        .collect::<Vec<_>>();
+    Ok(inputs
+        .iter()
+        .enumerate()
+        .filter(|(_, var)| matches!(circ.vars[**var].src, VarSrc::Input(_, _)))
+        .filter(|(i, _)| (0..distrs.len()).any(|x| distrs[x] != distrs[x ^ (1 << i)]))
+        .map(|(_, var)| *var as u32)
+        .collect())
+}
+
+/// Variables of `circ` on which `vars` depend (including themselves), in increasing order.
+pub(super) fn cone(circ: &SlSharedCircuit, vars: &[VarIdx]) -> Vec<VarIdx> {
+    let mut in_cone = vec![false; circ.vars.len()];
+    for v in vars.iter() {
+        in_cone[*v] = true;
+    }
+    for i in (0..circ.vars.len()).rev() {
+        if in_cone[i] {
+            for op in circ.vars[i].src.operands() {
+                in_cone[*op] = true;
+            }
+        }
+    }
+    (0..circ.vars.len()).filter(|i| in_cone[*i]).collect()
+}
+
+/// Evaluate the (sorted) `cone` on 64 lanes, the words of inputs, randoms and public values must
+/// be set.
+pub(super) fn eval_bitsliced(circ: &SlSharedCircuit, cone: &[VarIdx], words: &mut [u64]) {
+    for var in cone.iter().copied() {
+        words[var] = match &circ.vars[var].src {
+            VarSrc::Input(_, _) | VarSrc::Random | VarSrc::Public(_) => words[var],
+            VarSrc::Const(value) => 0u64.wrapping_sub(*value as u64),
+            VarSrc::Sum(ops) => ops.iter().fold(0, |acc, op| acc ^ words[*op]),
+            VarSrc::Product(ops) => ops.iter().fold(!0, |acc, op| acc & words[*op]),
+            VarSrc::Not([a]) => !words[*a],
+            VarSrc::Reg([a]) => words[*a],
+        };
+    }
+}
+
+/// Distribution (sorted histogram) of the probes, for the values `x` of the inputs.
+fn probes_distribution(
+    circ: &SlSharedCircuit,
+    cone: &[VarIdx],
+    inputs: &[VarIdx],
+    randoms: &[VarIdx],
+    probes: &[VarIdx],
+    x: u64,
+) -> Vec<(u64, u64)> {
+    let mut words = vec![0u64; circ.vars.len()];
+    for (i, var) in inputs.iter().enumerate() {
+        words[*var] = 0u64.wrapping_sub((x >> i) & 1);
+    }
+    let n_lanes = 1 << randoms.len().min(6);
+    let mut hist: HashMap<u64, u64> = HashMap::new();
+    for block in 0..(1u64 << randoms.len().saturating_sub(6)) {
+        for (j, var) in randoms.iter().enumerate() {
+            words[*var] = match LANE_PATTERNS.get(j) {
+                Some(pattern) => *pattern,
+                None => 0u64.wrapping_sub((block >> (j - 6)) & 1),
+            };
+        }
+        eval_bitsliced(circ, cone, &mut words);
+        for lane in 0..n_lanes {
+            let value = probes
+                .iter()
+                .enumerate()
+                .fold(0, |acc, (k, p)| acc | (((words[*p] >> lane) & 1) << k));
+            *hist.entry(value).or_insert(0) += 1;
+        }
+    }
+    let mut hist = hist.into_iter().collect::<Vec<_>>();
+    hist.sort_unstable();
+    hist
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::{build_isw, sim_set_with, SimMethod, Var};
+    use rand::seq::SliceRandom;
+    use rand::SeedableRng;
+
+    /// Both simulation methods require at least the input shares found by exhaustive simulation,
+    /// and Gaussian elimination never requires more than the heuristic.
+    #[test]
+    fn test_sim_methods_soundness() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+        for n_shares in 2..=3 {
+            let circ = build_isw(n_shares);
+            let vars = (0..circ.vars.len() as u32).collect::<Vec<_>>();
+            for _ in 0..100 {
+                let probes = vars
+                    .choose_multiple(&mut rng, n_shares)
+                    .copied()
+                    .collect::<Vec<_>>();
+                let exact = sim_set_exhaustive(&circ, probes.iter().copied()).unwrap();
+                let heuristic = sim_set_with(&circ, probes.iter().copied(), SimMethod::Heuristic);
+                let linalg = sim_set_with(&circ, probes.iter().copied(), SimMethod::LinAlg);
+                let subset = |a: &[u32], b: &[u32]| a.iter().all(|x| b.contains(x));
+                assert!(subset(&exact, &linalg), "probes: {:?}", probes);
+                assert!(subset(&linalg, &heuristic), "probes: {:?}", probes);
+            }
+        }
+    }
+
+    #[test]
+    fn test_exhaustive_product() {
+        let circ = build_isw(2);
+        let p_0_0 = circ.vars.iter().position(|v| v.name == "p_0_0").unwrap() as u32;
+        let p_0_1 = circ.vars.iter().position(|v| v.name == "p_0_1").unwrap() as u32;
+        // x_0 * y_0 and x_0 * y_1
+        assert_eq!(sim_set_exhaustive(&circ, vec![p_0_0]).unwrap(), vec![0, 2]);
+        assert_eq!(
+            sim_set_exhaustive(&circ, vec![p_0_0, p_0_1]).unwrap(),
+            vec![0, 2, 3]
+        );
+    }
+
+    #[test]
+    fn test_public() {
+        // a0 + p*r is not masked when p = 0, a0 + r + p is.
+        let vars = vec![
+            Var::new(VarSrc::Input(0, 0), None, "a0"),
+            Var::new(VarSrc::Input(0, 1), None, "a1"),
+            Var::new(VarSrc::Random, None, "r"),
+            Var::new(VarSrc::Public("p".to_owned()), None, "p"),
+            Var::new(VarSrc::Product(vec![3, 2]), None, "pr"),
+            Var::new(VarSrc::Sum(vec![0, 4]), Some((0, 0)), "s"),
+            Var::new(VarSrc::Sum(vec![0, 2]), None, "t"),
+            Var::new(VarSrc::Sum(vec![6, 3]), None, "u"),
+            Var::new(VarSrc::Not([1]), Some((0, 1)), "o1"),
+        ];
+        let circ = crate::circuit::new_sl_sc(vars, 2, 1, 1).unwrap();
+        for (probe, expected) in [(5, vec![0]), (7, vec![])] {
+            assert_eq!(sim_set_exhaustive(&circ, vec![probe]).unwrap(), expected);
+            for method in [SimMethod::Heuristic, SimMethod::LinAlg] {
+                assert_eq!(sim_set_with(&circ, vec![probe], method), expected);
+            }
+        }
+    }
+}
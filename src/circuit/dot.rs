@@ -0,0 +1,158 @@
+// STRAPS - Statistical Testing of RAndom Probing Security
+// Copyright (C) 2021 UCLouvain
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Graphviz export of circuits.
+
+use super::sl_sc::{SlSharedCircuit, VarSrc};
+use std::fmt::Write;
+
+/// Number of colors of the `ylorrd9` color scheme used for scores.
+const N_COLORS: f64 = 9.0;
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+impl SlSharedCircuit {
+    /// Graphviz (DOT) rendering of the circuit.
+    ///
+    /// Input and output shares are grouped by sharing and randoms are highlighted. If `scores`
+    /// is given (one value per variable, e.g. its use count), variables and the wires they drive
+    /// are colored from light (lowest score) to dark red (highest score).
+    pub fn to_dot(&self, scores: Option<&[f64]>) -> Result<String, String> {
+        let colors = scores.map(|scores| self.score_colors(scores)).transpose()?;
+        let mut res = String::new();
+        // Writing to a String cannot fail.
+        let mut w = |line: String| writeln!(res, "{}", line).unwrap();
+        w("digraph circuit {".to_owned());
+        w("  node [shape=box, style=filled, fillcolor=white, colorscheme=ylorrd9];".to_owned());
+        w("  edge [colorscheme=ylorrd9];".to_owned());
+        let node = |i: usize| {
+            let var = &self.vars[i];
+            let (op, shape) = match var.src {
+                VarSrc::Input(..) => (String::new(), "box"),
+                VarSrc::Random => (String::new(), "diamond"),
+                VarSrc::Public(_) => ("\\npublic".to_owned(), "house"),
+                VarSrc::Const(value) => (format!("\\n{}", value as u8), "plaintext"),
+                VarSrc::Sum(_) => ("\\n+".to_owned(), "ellipse"),
+                VarSrc::Product(_) => ("\\n*".to_owned(), "ellipse"),
+                VarSrc::Not(_) => ("\\n!".to_owned(), "ellipse"),
+                VarSrc::Reg(_) => ("\\nreg".to_owned(), "box3d"),
+            };
+            let mut attrs = format!("label=\"{}{}\", shape={}", escape(&var.name), op, shape);
+            match (&colors, &var.src) {
+                (Some(colors), _) => write!(
+                    attrs,
+                    ", fillcolor={}, tooltip=\"{}\"",
+                    colors[i],
+                    scores.unwrap()[i]
+                )
+                .unwrap(),
+                (None, VarSrc::Random) => attrs.push_str(", fillcolor=gold"),
+                (None, _) => {}
+            }
+            if let VarSrc::Random = var.src {
+                attrs.push_str(", penwidth=2");
+            }
+            format!("v{} [{}];", i, attrs)
+        };
+        // Clusters of input and output sharings, a variable that is both input and output share
+        // is drawn in its input sharing.
+        let clusters = (0..self.n_input_ports)
+            .map(|p| (format!("input {}", p), Some(p), None))
+            .chain((0..self.n_output_ports).map(|p| (format!("output {}", p), None, Some(p))));
+        let mut in_cluster = vec![false; self.vars.len()];
+        for (k, (label, in_port, out_port)) in clusters.enumerate() {
+            w(format!("  subgraph cluster_{} {{", k));
+            w(format!("    label=\"{}\";", label));
+            for (i, var) in self.vars.iter().enumerate() {
+                let in_sharing = match (&var.src, var.output_port) {
+                    (VarSrc::Input(p, _), _) => in_port == Some(*p),
+                    (_, Some((p, _))) => out_port == Some(p),
+                    _ => false,
+                };
+                if in_sharing {
+                    in_cluster[i] = true;
+                    w(format!("    {}", node(i)));
+                }
+            }
+            w("  }".to_owned());
+        }
+        for i in (0..self.vars.len()).filter(|i| !in_cluster[*i]) {
+            w(format!("  {}", node(i)));
+        }
+        for (i, var) in self.vars.iter().enumerate() {
+            for op in var.src.operands() {
+                match &colors {
+                    Some(colors) => w(format!("  v{} -> v{} [color={}];", op, i, colors[*op])),
+                    None => w(format!("  v{} -> v{};", op, i)),
+                }
+            }
+        }
+        w("}".to_owned());
+        Ok(res)
+    }
+
+    /// Index in the color scheme of each score.
+    fn score_colors(&self, scores: &[f64]) -> Result<Vec<usize>, String> {
+        if scores.len() != self.vars.len() {
+            return Err(format!(
+                "{} scores given for {} variables",
+                scores.len(),
+                self.vars.len()
+            ));
+        }
+        if scores.iter().any(|s| !s.is_finite()) {
+            return Err("scores must be finite".to_owned());
+        }
+        let min = scores.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = scores.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        Ok(scores
+            .iter()
+            .map(|s| {
+                if max > min {
+                    1 + ((s - min) / (max - min) * (N_COLORS - 1.0)).round() as usize
+                } else {
+                    1
+                }
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::circuit::{build_isw, VarSrc};
+
+    #[test]
+    fn test_to_dot() {
+        let circ = build_isw(2);
+        let dot = circ.to_dot(None).unwrap();
+        assert_eq!(dot.matches("subgraph").count(), 3);
+        let n_randoms = circ.vars.iter().filter(|v| v.src == VarSrc::Random).count();
+        assert_eq!(dot.matches("fillcolor=gold").count(), n_randoms);
+        let n_edges = circ.vars.iter().map(|v| v.src.operands().len()).sum();
+        assert_eq!(dot.matches("->").count(), n_edges);
+        let scores = circ
+            .var_use_counts()
+            .into_iter()
+            .map(f64::from)
+            .collect::<Vec<_>>();
+        let dot = circ.to_dot(Some(&scores)).unwrap();
+        assert!(dot.contains("fillcolor=9"));
+        assert!(circ.to_dot(Some(&scores[1..])).is_err());
+    }
+}
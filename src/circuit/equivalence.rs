@@ -0,0 +1,355 @@
+// STRAPS - Statistical Testing of RAndom Probing Security
+// Copyright (C) 2021 UCLouvain
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Equivalence checking of circuits, by comparison of ANFs.
+
+use super::poly::Polynomial;
+use super::sl_sc::{SlSharedCircuit, Var, VarSrc};
+use super::var_set::VarIdx;
+use std::collections::HashMap;
+
+/// What is compared by `SlSharedCircuit::equivalent`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Equivalence {
+    /// Each output share, as a polynomial in the input shares, the randoms and the public values.
+    /// Randoms and public values of both circuits are identified by name.
+    Shares,
+    /// The unmasked function of each output sharing (the sum of its shares), as a polynomial in
+    /// the unmasked inputs (the sums of the input sharings) and the public values. It may not
+    /// depend on the randoms, nor on the input shares otherwise than through the unmasked inputs.
+    /// Public values are identified by name.
+    Unmasked,
+}
+
+impl std::str::FromStr for Equivalence {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "shares" => Ok(Self::Shares),
+            "unmasked" => Ok(Self::Unmasked),
+            _ => Err(format!("unknown equivalence '{}'", s)),
+        }
+    }
+}
+
+/// Correspondence between the ports of two circuits: input (resp. output) port `p` of the other
+/// circuit is input (resp. output) port `inputs[p]` (resp. `outputs[p]`) of this one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PortMap {
+    pub inputs: Vec<usize>,
+    pub outputs: Vec<usize>,
+}
+
+impl PortMap {
+    pub fn identity(n_input_ports: usize, n_output_ports: usize) -> Self {
+        Self {
+            inputs: (0..n_input_ports).collect(),
+            outputs: (0..n_output_ports).collect(),
+        }
+    }
+}
+
+/// Evaluation on which two circuits differ.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Distinguisher {
+    /// Output port (of `self`), and output share if comparing shares.
+    pub output_port: usize,
+    pub share: Option<usize>,
+    /// Input shares, randoms and public values that are 1, all others are 0. Names are those of
+    /// `self`, except for randoms, public values and input shares that only exist in the other
+    /// circuit. When comparing unmasked functions, the first share of an input stands for the
+    /// unmasked input: the first share is the sum of the unmasked input and of the other shares.
+    pub ones: Vec<String>,
+    /// Value of the output (share) in `self`, the other circuit has the opposite value.
+    pub value: bool,
+}
+
+impl SlSharedCircuit {
+    /// Check whether `self` and `other` compute the same outputs, see [`Equivalence`]. Returns
+    /// an evaluation on which they differ, if any.
+    pub fn equivalent(
+        &self,
+        other: &SlSharedCircuit,
+        port_map: &PortMap,
+        mode: Equivalence,
+    ) -> Result<Option<Distinguisher>, String> {
+        let check_map = |map: &[usize], n_other, n_self, kind| {
+            let mut seen = vec![false; n_self];
+            if map.len() != n_other || n_other != n_self {
+                return Err(format!(
+                    "{} port map of length {} for {} and {} ports",
+                    kind,
+                    map.len(),
+                    n_other,
+                    n_self
+                ));
+            }
+            for p in map.iter() {
+                match seen.get_mut(*p) {
+                    Some(false) => seen[*p] = true,
+                    _ => return Err(format!("invalid {} port map {:?}", kind, map)),
+                }
+            }
+            Ok(())
+        };
+        check_map(
+            &port_map.inputs,
+            other.n_input_ports,
+            self.n_input_ports,
+            "input",
+        )?;
+        check_map(
+            &port_map.outputs,
+            other.n_output_ports,
+            self.n_output_ports,
+            "output",
+        )?;
+        if mode == Equivalence::Shares && other.n_shares != self.n_shares {
+            return Err(format!(
+                "circuits have {} and {} shares",
+                self.n_shares, other.n_shares
+            ));
+        }
+        let self_inputs = self.input_sharings()?;
+        let self_outputs = self.output_sharings()?;
+        let other_outputs = other.output_sharings()?;
+        // Variables of `other` mapped into the variables of `self`, randoms and public values of
+        // `other` that do not exist in `self` are numbered after the variables of `self`.
+        let key = |var: &Var| match &var.src {
+            VarSrc::Random => Some((false, var.name.clone())),
+            VarSrc::Public(name) => Some((true, name.clone())),
+            _ => None,
+        };
+        let self_named = self
+            .vars
+            .iter()
+            .enumerate()
+            .filter_map(|(i, var)| key(var).map(|k| (k, i)))
+            .collect::<HashMap<_, _>>();
+        let mut extra_named = Vec::new();
+        let other2self = other
+            .vars
+            .iter()
+            .map(|var| match (mode, &var.src) {
+                (_, VarSrc::Input(port, share)) if *share < self.n_shares => {
+                    Some(self_inputs[port_map.inputs[*port]][*share])
+                }
+                // Randoms are independent in both circuits when comparing unmasked functions.
+                (_, VarSrc::Input(..)) | (Equivalence::Unmasked, VarSrc::Random) => {
+                    extra_named.push(var.name.clone());
+                    Some(self.vars.len() + extra_named.len() - 1)
+                }
+                (Equivalence::Shares, VarSrc::Random) | (_, VarSrc::Public(_)) => {
+                    let key = key(var).unwrap();
+                    Some(self_named.get(&key).copied().unwrap_or_else(|| {
+                        extra_named.push(var.name.clone());
+                        self.vars.len() + extra_named.len() - 1
+                    }))
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        // Input sharings of `other`, as variables of `self`, indexed by input port of `self`.
+        let mut other_inputs = vec![Vec::new(); self.n_input_ports];
+        for (port, shares) in other.input_sharings()?.into_iter().enumerate() {
+            other_inputs[port_map.inputs[port]] =
+                shares.into_iter().map(|v| other2self[v].unwrap()).collect();
+        }
+        // Replace the first share of each input by the sum of the unmasked input (which takes the
+        // variable of the first share) and of the other shares.
+        let unmask = |poly: &Polynomial, sharings: &[Vec<VarIdx>]| {
+            let first_shares = sharings
+                .iter()
+                .map(|shares| {
+                    let sum = shares
+                        .iter()
+                        .copied()
+                        .map(Polynomial::from_var)
+                        .fold(Polynomial::zero(), |acc, x| acc + x);
+                    (shares[0], sum)
+                })
+                .collect::<HashMap<_, _>>();
+            poly.terms()
+                .map(|mon| {
+                    mon.variables::<VarIdx>().fold(Polynomial::one(), |acc, v| {
+                        acc * first_shares
+                            .get(&v)
+                            .cloned()
+                            .unwrap_or_else(|| Polynomial::from_var(v))
+                    })
+                })
+                .fold(Polynomial::zero(), |acc, x| acc + x)
+        };
+        let name = |v: VarIdx| {
+            self.vars
+                .get(v)
+                .map(|var| var.name.clone())
+                .unwrap_or_else(|| extra_named[v - self.vars.len()].clone())
+        };
+        for (other_port, other_shares) in other_outputs.iter().enumerate() {
+            let port = port_map.outputs[other_port];
+            let pairs: Vec<(Option<usize>, Polynomial, Polynomial)> = match mode {
+                Equivalence::Shares => other_shares
+                    .iter()
+                    .enumerate()
+                    .map(|(share, var)| {
+                        (
+                            Some(share),
+                            self.anfs[self_outputs[port][share]].clone(),
+                            other.anfs[*var].map_vars(|v: VarIdx| other2self[v]),
+                        )
+                    })
+                    .collect(),
+                Equivalence::Unmasked => {
+                    let sum = |circ: &SlSharedCircuit, shares: &[VarIdx]| {
+                        shares
+                            .iter()
+                            .fold(Polynomial::zero(), |acc, var| acc + &circ.anfs[*var])
+                    };
+                    let other_sum = sum(other, other_shares).map_vars(|v: VarIdx| other2self[v]);
+                    vec![(
+                        None,
+                        unmask(&sum(self, &self_outputs[port]), &self_inputs),
+                        unmask(&other_sum, &other_inputs),
+                    )]
+                }
+            };
+            for (share, self_poly, other_poly) in pairs {
+                let diff = &self_poly + &other_poly;
+                // See `check_function`.
+                if let Some(mon) = diff.terms().min_by_key(|mon| mon.degree()) {
+                    let ones = mon.variable_set();
+                    return Ok(Some(Distinguisher {
+                        output_port: port,
+                        share,
+                        ones: mon.variables::<VarIdx>().map(name).collect(),
+                        value: self_poly
+                            .terms()
+                            .filter(|m| m.variable_set().is_subset(ones))
+                            .count()
+                            % 2
+                            == 1,
+                    }));
+                }
+                if mode == Equivalence::Unmasked {
+                    let not_unmasked = self_poly.variables::<VarIdx>().into_iter().find(|v| {
+                        !matches!(self.vars[*v].src, VarSrc::Input(_, 0) | VarSrc::Public(_))
+                    });
+                    if let Some(v) = not_unmasked {
+                        return Err(format!(
+                            "output port {} of both circuits depends on {}, not only on the unmasked inputs",
+                            port,
+                            name(v)
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::{build_isw, new_sl_sc};
+
+    #[test]
+    fn test_equivalent() {
+        let isw = build_isw(2);
+        let id = PortMap::identity(2, 1);
+        assert_eq!(isw.equivalent(&isw, &id, Equivalence::Shares), Ok(None));
+        // Swapping the inputs of an AND changes the shares, but not the function.
+        let swap = PortMap {
+            inputs: vec![1, 0],
+            outputs: vec![0],
+        };
+        let diff = isw
+            .equivalent(&isw, &swap, Equivalence::Shares)
+            .unwrap()
+            .unwrap();
+        assert_eq!(diff.share, Some(0));
+        assert_eq!(isw.equivalent(&isw, &swap, Equivalence::Unmasked), Ok(None));
+        assert_eq!(
+            isw.equivalent(&build_isw(3), &id, Equivalence::Unmasked),
+            Ok(None)
+        );
+        // XOR gadget
+        let xor = new_sl_sc(
+            vec![
+                Var::new(VarSrc::Input(0, 0), None, "a0"),
+                Var::new(VarSrc::Input(0, 1), None, "a1"),
+                Var::new(VarSrc::Input(1, 0), None, "b0"),
+                Var::new(VarSrc::Input(1, 1), None, "b1"),
+                Var::new(VarSrc::Sum(vec![0, 2]), Some((0, 0)), "c0"),
+                Var::new(VarSrc::Sum(vec![1, 3]), Some((0, 1)), "c1"),
+            ],
+            2,
+            2,
+            1,
+        )
+        .unwrap();
+        let diff = isw
+            .equivalent(&xor, &id, Equivalence::Unmasked)
+            .unwrap()
+            .unwrap();
+        assert_eq!(diff.output_port, 0);
+        assert_eq!(diff.share, None);
+        assert_eq!(diff.ones.len(), 1);
+        assert!(!diff.value);
+        assert!(isw.equivalent(&xor, &swap, Equivalence::Shares).is_ok());
+        // XOR gadgets that are only correct when the random, resp. the second share of b, is 0.
+        // Comparing one of them with itself gives a distinguisher (for the random, as randoms
+        // are independent in both circuits) or an error (for b1).
+        let bad_xors = [
+            vec![
+                Var::new(VarSrc::Input(0, 0), None, "a0"),
+                Var::new(VarSrc::Input(0, 1), None, "a1"),
+                Var::new(VarSrc::Input(1, 0), None, "b0"),
+                Var::new(VarSrc::Input(1, 1), None, "b1"),
+                Var::new(VarSrc::Random, None, "r"),
+                Var::new(VarSrc::Sum(vec![0, 2]), None, "t"),
+                Var::new(VarSrc::Sum(vec![5, 4]), Some((0, 0)), "c0"),
+                Var::new(VarSrc::Sum(vec![1, 3]), Some((0, 1)), "c1"),
+            ],
+            vec![
+                Var::new(VarSrc::Input(0, 0), None, "a0"),
+                Var::new(VarSrc::Input(0, 1), None, "a1"),
+                Var::new(VarSrc::Input(1, 0), None, "b0"),
+                Var::new(VarSrc::Input(1, 1), None, "b1"),
+                Var::new(VarSrc::Sum(vec![0, 2]), Some((0, 0)), "c0"),
+                Var::new(VarSrc::Product(vec![1, 3]), None, "t"),
+                Var::new(VarSrc::Sum(vec![1, 5]), Some((0, 1)), "c1"),
+            ],
+        ];
+        for bad_xor in bad_xors.iter() {
+            let bad_xor = new_sl_sc(bad_xor.clone(), 2, 2, 1).unwrap();
+            let diff = xor
+                .equivalent(&bad_xor, &id, Equivalence::Unmasked)
+                .unwrap()
+                .unwrap();
+            assert_eq!(diff.ones.len(), 1);
+            assert!(["r", "b1"].contains(&diff.ones[0].as_str()));
+            assert_ne!(
+                bad_xor.equivalent(&bad_xor, &id, Equivalence::Unmasked),
+                Ok(None)
+            );
+        }
+        assert!(isw
+            .equivalent(&build_isw(3), &id, Equivalence::Shares)
+            .is_err());
+    }
+}
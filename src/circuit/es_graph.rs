@@ -54,6 +54,21 @@ impl CompGraphWork {
         debug!("from_circ_probes: Cleaned up graph");
         return (res, probes);
     }
+    /// Inputs on which depend the set of probes, after Gaussian elimination of the randoms that
+    /// appear linearly in the probes.
+    pub(crate) fn inputs_linalg(&self) -> Vec<u32> {
+        let anfs = self
+            .vars
+            .iter()
+            .positions(|v| v.probed)
+            .map(|p| self.build_anf_erased(p))
+            .collect::<Vec<_>>();
+        super::lin_sim::required_vars(anfs, |v| self.vars[v].src == VarSrc::Random)
+            .into_iter()
+            .filter(|v| matches!(self.vars[*v].src, VarSrc::Input(_, _)))
+            .map(|x| x as u32)
+            .collect()
+    }
     /// Inputs on which depend the set of probes
     pub(crate) fn inputs(&self) -> Vec<u32> {
         return self
@@ -84,11 +99,18 @@ pub(crate) struct CompGraphWork {
 
 impl CompGraphWork {
     fn build_anf_erased(&self, var: VarIdx) -> Polynomial {
-        match self.vars[var].src {
-            VarSrc::Input(_, _) | VarSrc::Random => Polynomial::from_var(var),
+        match &self.vars[var].src {
+            VarSrc::Input(_, _) | VarSrc::Random | VarSrc::Public(_) => Polynomial::from_var(var),
+            VarSrc::Const(false) => Polynomial::zero(),
+            VarSrc::Const(true) => Polynomial::one(),
             VarSrc::Not(it) => self.build_anf_erased(it[0]).not(),
-            VarSrc::Sum(it) => self.build_anf_erased(it[0]) + self.build_anf_erased(it[1]),
-            VarSrc::Product(it) => self.build_anf_erased(it[0]) * self.build_anf_erased(it[1]),
+            VarSrc::Reg(it) => self.build_anf_erased(it[0]),
+            VarSrc::Sum(it) => it.iter().fold(Polynomial::zero(), |acc, op| {
+                acc + self.build_anf_erased(*op)
+            }),
+            VarSrc::Product(it) => it.iter().fold(Polynomial::one(), |acc, op| {
+                acc * self.build_anf_erased(*op)
+            }),
         }
     }
 
@@ -200,10 +222,13 @@ impl CompGraphWork {
             debug!("Remaning inputs of {:?}", p);
             for t in self.build_anf_erased(p).variables::<usize>() {
                 debug!("term {:?}", t);
-                if let VarSrc::Input(_, _) = self.vars[t].src {
-                    required_inputs.insert(t);
-                } else {
-                    assert!(self.vars[t].src == VarSrc::Random);
+                match self.vars[t].src {
+                    VarSrc::Input(_, _) => {
+                        required_inputs.insert(t);
+                    }
+                    // Public values are known by the simulator.
+                    VarSrc::Public(_) => {}
+                    _ => assert!(self.vars[t].src == VarSrc::Random),
                 }
             }
         }
@@ -0,0 +1,220 @@
+// STRAPS - Statistical Testing of RAndom Probing Security
+// Copyright (C) 2021 UCLouvain
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Functional correctness of masked circuits.
+//!
+//! The ANF of the sum of the shares of each output sharing is compared with the ANF of the
+//! specification in which each unmasked input is replaced by the sum of its shares. Since ANFs
+//! are canonical, they are equal iff the outputs are correct for all values of the randoms.
+
+use super::poly::Polynomial;
+use super::sl_sc::{SlSharedCircuit, VarSrc};
+use super::var_set::VarIdx;
+
+const NOT_UNMASKED: &str = "specification circuit uses randoms or public values";
+
+/// Boolean function of the unmasked inputs, for each output sharing.
+#[derive(Debug, Clone)]
+pub enum FunctionSpec {
+    /// For each output, its monomials given as the sets of input ports they contain (the empty
+    /// set is the constant 1).
+    Anf(Vec<Vec<Vec<usize>>>),
+    /// For each output, its value for each input, where bit `p` of the index is input `p`.
+    TruthTable(Vec<Vec<bool>>),
+    /// An unmasked (single-share, without randoms) circuit.
+    Circuit(SlSharedCircuit),
+}
+
+/// Evaluation of the circuit on which an output sharing is wrong.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionMismatch {
+    pub output_port: usize,
+    /// Input shares and randoms that are 1, all others are 0.
+    pub ones: Vec<String>,
+    /// Value of the specification.
+    pub expected: bool,
+}
+
+impl SlSharedCircuit {
+    /// Check that the sum of each output sharing is the function `spec` of the unmasked inputs,
+    /// for all values of the randoms. Returns an evaluation on which this does not hold, if any.
+    /// The specification does not depend on public values, hence neither may the outputs.
+    pub fn check_function(&self, spec: &FunctionSpec) -> Result<Option<FunctionMismatch>, String> {
+        let spec_anfs = self.spec_anfs(spec)?;
+        let unmasked_inputs = self
+            .input_sharings()?
+            .into_iter()
+            .map(|shares| {
+                shares
+                    .into_iter()
+                    .map(Polynomial::from_var)
+                    .fold(Polynomial::zero(), |acc, x| acc + x)
+            })
+            .collect::<Vec<_>>();
+        let output_sharings = self.output_sharings()?;
+        for (port, (shares, spec_anf)) in output_sharings.iter().zip(spec_anfs.iter()).enumerate() {
+            let actual = shares
+                .iter()
+                .copied()
+                .fold(Polynomial::zero(), |acc, var| acc + &self.anfs[var]);
+            let expected = spec_anf
+                .iter()
+                .map(|mon| {
+                    mon.iter().fold(Polynomial::one(), |acc, input| {
+                        acc * &unmasked_inputs[*input]
+                    })
+                })
+                .fold(Polynomial::zero(), |acc, x| acc + x);
+            let diff = &actual + &expected;
+            // All monomials of the difference but the one of lowest degree vanish when the
+            // variables of that monomial are 1 and all others 0.
+            if let Some(mon) = diff.terms().min_by_key(|mon| mon.degree()) {
+                let ones = mon.variable_set();
+                return Ok(Some(FunctionMismatch {
+                    output_port: port,
+                    ones: mon
+                        .variables::<VarIdx>()
+                        .map(|v| self.vars[v].name.clone())
+                        .collect(),
+                    expected: expected
+                        .terms()
+                        .filter(|m| m.variable_set().is_subset(ones))
+                        .count()
+                        % 2
+                        == 1,
+                }));
+            }
+        }
+        Ok(None)
+    }
+
+    /// ANF of `spec`, as sets of input ports.
+    fn spec_anfs(&self, spec: &FunctionSpec) -> Result<Vec<Vec<Vec<usize>>>, String> {
+        let n_inputs = self.n_input_ports;
+        let res = match spec {
+            FunctionSpec::Anf(anfs) => anfs.clone(),
+            FunctionSpec::TruthTable(tables) => tables
+                .iter()
+                .map(|table| {
+                    if table.len() != 1 << n_inputs {
+                        return Err(format!(
+                            "truth table has {} entries, expected {}",
+                            table.len(),
+                            1 << n_inputs
+                        ));
+                    }
+                    // Moebius transform
+                    let mut coefs = table.clone();
+                    for i in 0..n_inputs {
+                        for x in 0..coefs.len() {
+                            if x & (1 << i) != 0 {
+                                coefs[x] ^= coefs[x ^ (1 << i)];
+                            }
+                        }
+                    }
+                    Ok((0..coefs.len())
+                        .filter(|x| coefs[*x])
+                        .map(|x| (0..n_inputs).filter(|i| x & (1 << i) != 0).collect())
+                        .collect())
+                })
+                .collect::<Result<_, _>>()?,
+            FunctionSpec::Circuit(circ) => {
+                if circ.n_shares != 1 || circ.n_input_ports != n_inputs {
+                    return Err(format!(
+                        "specification circuit must have 1 share and {} inputs",
+                        n_inputs
+                    ));
+                }
+                let mut outputs = vec![None; circ.n_output_ports];
+                for (i, var) in circ.vars.iter().enumerate() {
+                    if let Some((port, _)) = var.output_port {
+                        outputs[port] = Some(i);
+                    }
+                }
+                outputs
+                    .into_iter()
+                    .enumerate()
+                    .map(|(port, var)| {
+                        let var = var.ok_or_else(|| format!("missing output {}", port))?;
+                        circ.anfs[var]
+                            .terms()
+                            .map(|mon| {
+                                mon.variables::<VarIdx>()
+                                    .map(|v| match circ.vars[v].src {
+                                        VarSrc::Input(port, _) => Ok(port),
+                                        _ => Err(NOT_UNMASKED.to_owned()),
+                                    })
+                                    .collect()
+                            })
+                            .collect()
+                    })
+                    .collect::<Result<_, String>>()?
+            }
+        };
+        if res.len() != self.n_output_ports {
+            return Err(format!(
+                "specification has {} outputs, expected {}",
+                res.len(),
+                self.n_output_ports
+            ));
+        }
+        if let Some(input) = res.iter().flatten().flatten().find(|i| **i >= n_inputs) {
+            return Err(format!("input {} does not exist", input));
+        }
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::{build_isw, new_sl_sc, Var};
+
+    #[test]
+    fn test_check_function() {
+        let circ = build_isw(3);
+        let and = FunctionSpec::TruthTable(vec![vec![false, false, false, true]]);
+        assert_eq!(circ.check_function(&and), Ok(None));
+        assert_eq!(
+            circ.check_function(&FunctionSpec::Anf(vec![vec![vec![0, 1]]])),
+            Ok(None)
+        );
+        let unmasked_and = new_sl_sc(
+            vec![
+                Var::new(VarSrc::Input(0, 0), None, "x"),
+                Var::new(VarSrc::Input(1, 0), None, "y"),
+                Var::new(VarSrc::Product(vec![0, 1]), Some((0, 0)), "z"),
+            ],
+            1,
+            2,
+            1,
+        )
+        .unwrap();
+        assert_eq!(
+            circ.check_function(&FunctionSpec::Circuit(unmasked_and)),
+            Ok(None)
+        );
+        // x + y + xy = x | y
+        let or = FunctionSpec::Anf(vec![vec![vec![0], vec![1], vec![0, 1]]]);
+        let mismatch = circ.check_function(&or).unwrap().unwrap();
+        assert_eq!(mismatch.output_port, 0);
+        assert_eq!(mismatch.ones.len(), 1);
+        assert!(mismatch.expected);
+        assert!(circ
+            .check_function(&FunctionSpec::TruthTable(vec![vec![true; 2]]))
+            .is_err());
+    }
+}
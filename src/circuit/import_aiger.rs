@@ -0,0 +1,174 @@
+// STRAPS - Statistical Testing of RAndom Probing Security
+// Copyright (C) 2021 UCLouvain
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Import of combinational netlists in ASCII AIGER (`aag`) format.
+//!
+//! Inputs and outputs are named by the symbol table, or `i<k>`/`o<k>` when they have no symbol.
+
+use super::aig::{Aig, AigNode};
+use super::port_spec::PortSpec;
+use super::sl_sc::SlSharedCircuit;
+
+fn parse_line(line: &str, n: usize) -> Result<Vec<usize>, String> {
+    let nums = line
+        .split_whitespace()
+        .map(|x| {
+            x.parse::<usize>()
+                .map_err(|_| format!("invalid number '{}'", x))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if nums.len() != n {
+        return Err(format!("expected {} numbers", n));
+    }
+    Ok(nums)
+}
+
+/// Build a circuit from an ASCII AIGER netlist, with roles of the signals given by `spec`.
+pub fn parse_aiger(text: &str, spec: &PortSpec) -> Result<SlSharedCircuit, String> {
+    let mut lines = text.lines().enumerate().map(|(i, l)| (i + 1, l.trim()));
+    let (l, header) = lines.next().ok_or("empty file")?;
+    let header = header.split_whitespace().collect::<Vec<_>>();
+    match header.first() {
+        Some(&"aag") => {}
+        Some(&"aig") => return Err("only ASCII AIGER (aag) is supported".to_owned()),
+        _ => return Err(format!("line {}: missing 'aag' header", l)),
+    }
+    let counts = parse_line(&header[1..].join(" "), header.len() - 1)
+        .map_err(|e| format!("line {}: {}", l, e))?;
+    if counts.len() < 5 || counts[5..].iter().any(|x| *x != 0) {
+        return Err(format!(
+            "line {}: expected 'aag M I L O A' (no bad, constraint, justice or fairness)",
+            l
+        ));
+    }
+    let (m, n_in, n_latch, n_out, n_and) = (counts[0], counts[1], counts[2], counts[3], counts[4]);
+    if n_latch != 0 {
+        return Err(format!(
+            "line {}: latches are not supported (combinational netlists only)",
+            l
+        ));
+    }
+    let mut next_line = |n: usize| {
+        let (l, line) = lines.next().ok_or("unexpected end of file")?;
+        parse_line(line, n).map_err(|e| format!("line {}: {}", l, e))
+    };
+
+    let check_lit = |lit: usize| {
+        if lit > 2 * m + 1 {
+            Err(format!("literal {} is out of range", lit))
+        } else {
+            Ok(lit)
+        }
+    };
+    let mut defs: Vec<Option<AigNode>> = vec![None; m + 1];
+    let mut input_vars = Vec::with_capacity(n_in);
+    let mut output_lits = Vec::with_capacity(n_out);
+    for _ in 0..n_in {
+        let lit = next_line(1)?[0];
+        if lit < 2 || lit % 2 != 0 || check_lit(lit).is_err() || defs[lit / 2].is_some() {
+            return Err(format!("invalid input literal {}", lit));
+        }
+        defs[lit / 2] = Some(AigNode::Input(format!("i{}", input_vars.len())));
+        input_vars.push(lit / 2);
+    }
+    for _ in 0..n_out {
+        output_lits.push(check_lit(next_line(1)?[0])?);
+    }
+    for _ in 0..n_and {
+        let nums = next_line(3)?;
+        let lhs = nums[0];
+        if lhs < 2 || lhs % 2 != 0 || check_lit(lhs).is_err() || defs[lhs / 2].is_some() {
+            return Err(format!("invalid AND gate literal {}", lhs));
+        }
+        defs[lhs / 2] = Some(AigNode::And(check_lit(nums[1])?, check_lit(nums[2])?));
+    }
+    let mut output_names = (0..n_out).map(|k| format!("o{}", k)).collect::<Vec<_>>();
+    for (l, line) in lines {
+        if line == "c" {
+            break;
+        }
+        let mut parts = line.splitn(2, ' ');
+        let (kind, name) = match (parts.next(), parts.next()) {
+            (Some(kind), Some(name)) if kind.len() > 1 => (kind, name.to_owned()),
+            _ => return Err(format!("line {}: invalid symbol", l)),
+        };
+        let pos = kind[1..]
+            .parse::<usize>()
+            .map_err(|_| format!("line {}: invalid symbol", l))?;
+        match (&kind[..1], input_vars.get(pos), output_names.get_mut(pos)) {
+            ("i", Some(var), _) => defs[*var] = Some(AigNode::Input(name)),
+            ("o", _, Some(out_name)) => *out_name = name,
+            _ => return Err(format!("line {}: invalid symbol", l)),
+        }
+    }
+
+    let mut aig = Aig::new();
+    for (var, def) in defs.into_iter().enumerate().skip(1) {
+        match def {
+            Some(AigNode::Input(name)) => aig.add_input(name),
+            Some(AigNode::And(a, b)) => aig.add_and(a, b),
+            _ => return Err(format!("variable {} is not defined", var)),
+        };
+    }
+    for (name, lit) in output_names.into_iter().zip(output_lits) {
+        aig.set_name(lit, name.clone());
+        aig.set_output(name, lit);
+    }
+    aig.to_circuit(spec)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::VarSrc;
+
+    // 1-share "gadget" computing c = (a ^ b) & r, the XOR is made of 3 AND nodes.
+    const XOR_AND: &str = "aag 7 3 0 1 4
+2
+4
+6
+14
+8 2 4
+10 3 5
+12 9 11
+14 12 6
+i0 a
+i1 b
+i2 r
+o0 c
+c
+comment
+";
+
+    #[test]
+    fn test_parse_aiger() {
+        let spec = PortSpec::parse("input a\ninput b\nrandom r\noutput c").unwrap();
+        let circ = parse_aiger(XOR_AND, &spec).unwrap();
+        let srcs = circ.vars.iter().map(|v| v.src.clone()).collect::<Vec<_>>();
+        assert_eq!(
+            srcs,
+            vec![
+                VarSrc::Input(0, 0),
+                VarSrc::Input(1, 0),
+                VarSrc::Random,
+                VarSrc::Sum(vec![0, 1]),
+                VarSrc::Product(vec![2, 3]),
+            ]
+        );
+        assert_eq!(circ.vars[4].name, "c");
+        assert_eq!(circ.vars[4].output_port, Some((0, 0)));
+    }
+}
@@ -0,0 +1,232 @@
+// STRAPS - Statistical Testing of RAndom Probing Security
+// Copyright (C) 2021 UCLouvain
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Import of combinational netlists in BLIF format (single model, `.names` covers).
+
+use super::aig::{Aig, Lit};
+use super::port_spec::PortSpec;
+use super::sl_sc::SlSharedCircuit;
+use std::collections::HashMap;
+
+struct Cover {
+    line: usize,
+    inputs: Vec<String>,
+    rows: Vec<(String, char)>,
+}
+
+struct BlifBuilder {
+    aig: Aig,
+    covers: HashMap<String, Cover>,
+    signals: HashMap<String, Lit>,
+    // signals being built, to detect loops
+    pending: Vec<String>,
+}
+
+impl BlifBuilder {
+    fn signal(&mut self, name: &str) -> Result<Lit, String> {
+        if let Some(lit) = self.signals.get(name) {
+            return Ok(*lit);
+        }
+        if self.pending.iter().any(|s| s == name) {
+            return Err(format!("combinational loop through '{}'", name));
+        }
+        let cover = self
+            .covers
+            .remove(name)
+            .ok_or_else(|| format!("signal '{}' is not defined", name))?;
+        self.pending.push(name.to_owned());
+        let inputs = cover
+            .inputs
+            .iter()
+            .map(|s| self.signal(s))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.pending.pop();
+        let lit = self
+            .cover(&inputs, &cover.rows)
+            .map_err(|e| format!("line {}: {}", cover.line, e))?;
+        self.aig.set_name(lit, name.to_owned());
+        self.signals.insert(name.to_owned(), lit);
+        Ok(lit)
+    }
+
+    /// Sum-of-products of a cover.
+    fn cover(&mut self, inputs: &[Lit], rows: &[(String, char)]) -> Result<Lit, String> {
+        let mut cubes = Vec::with_capacity(rows.len());
+        for (cube, _) in rows.iter() {
+            if cube.len() != inputs.len() {
+                return Err(format!("cube '{}' does not match the inputs", cube));
+            }
+            let lits = cube
+                .chars()
+                .zip(inputs.iter())
+                .filter_map(|(c, lit)| match c {
+                    '1' => Some(Ok(*lit)),
+                    '0' => Some(Ok(*lit ^ 1)),
+                    '-' => None,
+                    _ => Some(Err(format!("invalid cube '{}'", cube))),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            cubes.push(self.aig.and_many(lits));
+        }
+        let on_set = !matches!(rows.first(), Some((_, '0')));
+        if rows
+            .iter()
+            .any(|(_, o)| *o != if on_set { '1' } else { '0' })
+        {
+            return Err("mixed or invalid cover outputs".to_owned());
+        }
+        let sop = self.aig.or_many(cubes);
+        Ok(if on_set { sop } else { sop ^ 1 })
+    }
+}
+
+/// Build a circuit from a BLIF netlist, with roles of the signals given by `spec`.
+pub fn parse_blif(text: &str, spec: &PortSpec) -> Result<SlSharedCircuit, String> {
+    let mut builder = BlifBuilder {
+        aig: Aig::new(),
+        covers: HashMap::new(),
+        signals: HashMap::new(),
+        pending: Vec::new(),
+    };
+    let mut outputs = Vec::new();
+    let mut current_cover: Option<(String, Cover)> = None;
+    let mut lines = text.lines().enumerate();
+    while let Some((i, line)) = lines.next() {
+        let line_no = i + 1;
+        let mut line = line.split('#').next().unwrap().trim().to_owned();
+        // Continuation lines
+        while line.ends_with('\\') {
+            line.pop();
+            let next = lines.next().map(|(_, l)| l).unwrap_or("");
+            line.push(' ');
+            line.push_str(next.split('#').next().unwrap().trim());
+        }
+        let tokens = line.split_whitespace().collect::<Vec<_>>();
+        let first = match tokens.first() {
+            None => continue,
+            Some(first) => *first,
+        };
+        if !first.starts_with('.') {
+            let (cover, cube, out) = match (current_cover.as_mut(), tokens.as_slice()) {
+                (Some((_, cover)), [out]) if cover.inputs.is_empty() => (cover, "", *out),
+                (Some((_, cover)), [cube, out]) => (cover, *cube, *out),
+                _ => return Err(format!("line {}: unexpected line", line_no)),
+            };
+            let out = match out {
+                "0" => '0',
+                "1" => '1',
+                o => return Err(format!("line {}: invalid cover output '{}'", line_no, o)),
+            };
+            cover.rows.push((cube.to_owned(), out));
+            continue;
+        }
+        if let Some((name, cover)) = current_cover.take() {
+            if builder.covers.insert(name.clone(), cover).is_some() {
+                return Err(format!("signal '{}' is defined twice", name));
+            }
+        }
+        match first {
+            ".model" => {}
+            ".inputs" => {
+                for name in tokens[1..].iter() {
+                    let lit = builder.aig.add_input((*name).to_owned());
+                    builder.signals.insert((*name).to_owned(), lit);
+                }
+            }
+            ".outputs" => outputs.extend(tokens[1..].iter().map(|s| (*s).to_owned())),
+            ".names" => {
+                let (out, inputs) = tokens[1..]
+                    .split_last()
+                    .ok_or_else(|| format!("line {}: .names without signal", line_no))?;
+                current_cover = Some((
+                    (*out).to_owned(),
+                    Cover {
+                        line: line_no,
+                        inputs: inputs.iter().map(|s| (*s).to_owned()).collect(),
+                        rows: Vec::new(),
+                    },
+                ));
+            }
+            ".end" => break,
+            kw => {
+                return Err(format!(
+                    "line {}: '{}' is not supported (single combinational model only)",
+                    line_no, kw
+                ));
+            }
+        }
+    }
+    if let Some((name, cover)) = current_cover.take() {
+        if builder.covers.insert(name.clone(), cover).is_some() {
+            return Err(format!("signal '{}' is defined twice", name));
+        }
+    }
+    for name in outputs {
+        let lit = builder.signal(&name)?;
+        builder.aig.set_output(name, lit);
+    }
+    builder.aig.to_circuit(spec)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::VarSrc;
+
+    const REFRESH_AND: &str = "
+.model refresh_and
+.inputs a0 a1 r \\
+  b
+.outputs c0 c1
+.names a0 r t0
+10 1
+01 1
+.names a1 r t1
+11 0
+00 0
+.names t0 b c0
+11 1
+.names t1 b c1
+0- 0
+-0 0
+.end
+";
+
+    #[test]
+    fn test_parse_blif() {
+        let spec = PortSpec::parse("input a0 a1\ninput b\nrandom r\noutput c0 c1");
+        assert!(spec.is_err());
+        let spec = PortSpec::parse("shares 2\ninput a0 a1\nrandom r b\noutput c0 c1").unwrap();
+        let circ = parse_blif(REFRESH_AND, &spec).unwrap();
+        let srcs = circ.vars.iter().map(|v| v.src.clone()).collect::<Vec<_>>();
+        assert_eq!(
+            srcs,
+            vec![
+                VarSrc::Input(0, 0),
+                VarSrc::Input(0, 1),
+                VarSrc::Random,
+                VarSrc::Random,
+                VarSrc::Sum(vec![0, 2]),
+                VarSrc::Product(vec![3, 4]),
+                VarSrc::Sum(vec![1, 2]),
+                VarSrc::Product(vec![3, 6]),
+            ]
+        );
+        assert_eq!(circ.vars[4].name, "t0");
+        assert_eq!(circ.vars[7].name, "c1");
+        assert_eq!(circ.vars[7].output_port, Some((0, 1)));
+    }
+}
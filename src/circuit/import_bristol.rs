@@ -0,0 +1,293 @@
+// STRAPS - Statistical Testing of RAndom Probing Security
+// Copyright (C) 2021 UCLouvain
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Import of circuits in Bristol Fashion format.
+//!
+//! The netlist itself does not tell which input wires are shares and which are randoms: this is
+//! given by a [`BristolRoles`].
+
+use super::import_sl_sc::new_sl_sc;
+use super::sl_sc::{SlSharedCircuit, Var, VarSrc};
+use super::var_set::VarIdx;
+
+/// Role of the wires of a Bristol Fashion netlist.
+#[derive(Debug, Clone, Default)]
+pub struct BristolRoles {
+    /// `inputs[port][share]` is the wire carrying that input share.
+    pub inputs: Vec<Vec<usize>>,
+    /// `outputs[port][share]` is the wire carrying that output share.
+    pub outputs: Vec<Vec<usize>>,
+    /// Input wires that are fresh randoms.
+    pub randoms: Vec<usize>,
+}
+
+impl BristolRoles {
+    fn n_shares(&self) -> Result<usize, String> {
+        let n_shares = self
+            .inputs
+            .iter()
+            .chain(self.outputs.iter())
+            .map(|port| port.len())
+            .next()
+            .ok_or("No input or output sharing")?;
+        if self
+            .inputs
+            .iter()
+            .chain(self.outputs.iter())
+            .any(|port| port.len() != n_shares)
+        {
+            return Err("All sharings must have the same number of shares".to_owned());
+        }
+        Ok(n_shares)
+    }
+}
+
+fn parse_numbers(line: &str) -> Result<Vec<usize>, String> {
+    line.split_whitespace()
+        .map(|x| {
+            x.parse::<usize>()
+                .map_err(|_| format!("invalid number '{}'", x))
+        })
+        .collect()
+}
+
+/// Parse a header line made of a count followed by that many numbers.
+fn parse_io_header(line: &str) -> Result<Vec<usize>, String> {
+    let nums = parse_numbers(line)?;
+    match nums.split_first() {
+        Some((n, sizes)) if *n == sizes.len() => Ok(sizes.to_vec()),
+        _ => Err("malformed input/output header".to_owned()),
+    }
+}
+
+enum Gate {
+    Var(VarSrc),
+    // EQW gate: the output wire carries an existing variable
+    Copy(VarIdx),
+}
+
+/// Parse a gate line, returns the gate and its output wire.
+fn parse_gate(line: &str, wires: &[Option<VarIdx>]) -> Result<(Gate, usize), String> {
+    let tokens = line.split_whitespace().collect::<Vec<_>>();
+    let (kind, nums) = tokens.split_last().ok_or("empty gate")?;
+    let nums = parse_numbers(&nums.join(" "))?;
+    let operand = |w: usize| {
+        wires
+            .get(w)
+            .copied()
+            .flatten()
+            .ok_or_else(|| format!("wire {} is used before being assigned", w))
+    };
+    let (gate, out) = match (*kind, &nums[..]) {
+        ("XOR", [2, 1, a, b, out]) | ("AND", [2, 1, a, b, out]) => {
+            let mut ops = vec![operand(*a)?, operand(*b)?];
+            ops.sort_unstable();
+            let src = if *kind == "XOR" {
+                VarSrc::Sum(ops)
+            } else {
+                VarSrc::Product(ops)
+            };
+            (Gate::Var(src), *out)
+        }
+        ("INV", [1, 1, a, out]) => (Gate::Var(VarSrc::Not([operand(*a)?])), *out),
+        ("EQW", [1, 1, a, out]) => (Gate::Copy(operand(*a)?), *out),
+        ("EQ", [1, 1, value @ (0 | 1), out]) => (Gate::Var(VarSrc::Const(*value == 1)), *out),
+        ("EQ", [1, 1, _, _]) => return Err("EQ gate constant must be 0 or 1".to_owned()),
+        ("XOR", _) | ("AND", _) | ("INV", _) | ("EQW", _) | ("EQ", _) => {
+            return Err(format!("wrong operand count for {} gate", kind));
+        }
+        _ => return Err(format!("unknown gate '{}'", kind)),
+    };
+    match wires.get(out) {
+        None => Err(format!("wire {} is out of range", out)),
+        Some(Some(_)) => Err(format!("wire {} is assigned twice", out)),
+        Some(None) => Ok((gate, out)),
+    }
+}
+
+/// Build a circuit from a Bristol Fashion netlist (XOR, AND, INV, EQW and EQ gates).
+///
+/// Every input wire of the netlist must be either an input share or a random in `roles`.
+pub fn parse_bristol(text: &str, roles: &BristolRoles) -> Result<SlSharedCircuit, String> {
+    let n_shares = roles.n_shares()?;
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .map(|(i, l)| (i + 1, l.trim()))
+        .filter(|(_, l)| !l.is_empty());
+    let mut header = || lines.next().ok_or_else(|| "truncated header".to_owned());
+    let (l, line) = header()?;
+    let counts = parse_numbers(line).map_err(|e| format!("line {}: {}", l, e))?;
+    let (n_gates, n_wires) = match counts[..] {
+        [n_gates, n_wires] => (n_gates, n_wires),
+        _ => return Err(format!("line {}: expected gate and wire counts", l)),
+    };
+    let (l, line) = header()?;
+    let n_in_wires: usize = parse_io_header(line)
+        .map_err(|e| format!("line {}: {}", l, e))?
+        .iter()
+        .sum();
+    let (l, line) = header()?;
+    parse_io_header(line).map_err(|e| format!("line {}: {}", l, e))?;
+    if n_in_wires > n_wires {
+        return Err("more input wires than wires".to_owned());
+    }
+
+    let mut vars = Vec::new();
+    let mut wires: Vec<Option<VarIdx>> = vec![None; n_wires];
+    let mut in_srcs: Vec<Option<VarSrc>> = vec![None; n_in_wires];
+    let mut set_in_src = |wire: usize, src: VarSrc| {
+        match in_srcs.get_mut(wire) {
+            None => return Err(format!("wire {} is not an input wire", wire)),
+            Some(Some(_)) => return Err(format!("wire {} has several roles", wire)),
+            Some(x) => *x = Some(src),
+        }
+        Ok(())
+    };
+    for (port, shares) in roles.inputs.iter().enumerate() {
+        for (share, wire) in shares.iter().enumerate() {
+            set_in_src(*wire, VarSrc::Input(port, share))?;
+        }
+    }
+    for wire in roles.randoms.iter() {
+        set_in_src(*wire, VarSrc::Random)?;
+    }
+    for (wire, src) in in_srcs.into_iter().enumerate() {
+        let src = src.ok_or_else(|| format!("input wire {} has no role", wire))?;
+        vars.push(Var {
+            src,
+            output_port: None,
+            name: format!("w{}", wire),
+        });
+        wires[wire] = Some(vars.len() - 1);
+    }
+
+    let mut gate_count = 0;
+    for (l, line) in lines {
+        let (gate, out) = parse_gate(line, &wires).map_err(|e| format!("line {}: {}", l, e))?;
+        wires[out] = Some(match gate {
+            Gate::Var(src) => {
+                vars.push(Var {
+                    src,
+                    output_port: None,
+                    name: format!("w{}", out),
+                });
+                vars.len() - 1
+            }
+            Gate::Copy(var) => var,
+        });
+        gate_count += 1;
+    }
+    if gate_count != n_gates {
+        return Err(format!(
+            "header announces {} gates, found {}",
+            n_gates, gate_count
+        ));
+    }
+
+    for (port, shares) in roles.outputs.iter().enumerate() {
+        for (share, wire) in shares.iter().enumerate() {
+            let var = wires
+                .get(*wire)
+                .copied()
+                .flatten()
+                .ok_or_else(|| format!("output wire {} is never assigned", wire))?;
+            if vars[var].output_port.is_some() {
+                return Err(format!(
+                    "output wire {} has the value of another output",
+                    wire
+                ));
+            }
+            vars[var].output_port = Some((port, share));
+        }
+    }
+    new_sl_sc(vars, n_shares, roles.inputs.len(), roles.outputs.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2-share refresh of a: o = (a0 + r, a1 + r), with a copy of the first output.
+    const REFRESH: &str = "
+        3 7
+        2 2 1
+        1 2
+
+        2 1 0 2 3 XOR
+        2 1 1 2 4 XOR
+        1 1 3 5 EQW
+    ";
+
+    #[test]
+    fn test_parse_refresh() {
+        let roles = BristolRoles {
+            inputs: vec![vec![0, 1]],
+            outputs: vec![vec![5, 4]],
+            randoms: vec![2],
+        };
+        let circ = parse_bristol(REFRESH, &roles).unwrap();
+        assert_eq!(circ.vars.len(), 5);
+        assert_eq!(circ.vars[3].src, VarSrc::Sum(vec![0, 2]));
+        assert_eq!(circ.vars[3].output_port, Some((0, 0)));
+        assert_eq!(circ.vars[4].output_port, Some((0, 1)));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let roles = BristolRoles {
+            inputs: vec![vec![0, 1]],
+            outputs: vec![vec![3, 4]],
+            randoms: vec![],
+        };
+        assert_eq!(
+            parse_bristol(REFRESH, &roles).unwrap_err(),
+            "input wire 2 has no role"
+        );
+        let roles = BristolRoles {
+            inputs: vec![vec![0, 1]],
+            outputs: vec![vec![3, 4]],
+            randoms: vec![2],
+        };
+        let text = REFRESH.replace("1 2 4 XOR", "1 6 4 XOR");
+        assert_eq!(
+            parse_bristol(&text, &roles).unwrap_err(),
+            "line 7: wire 6 is used before being assigned"
+        );
+    }
+
+    #[test]
+    fn test_parse_const() {
+        // Complement of both shares: o = (a0 + 1, a1 + 1)
+        let text = "
+            3 5
+            1 2
+            1 2
+
+            1 1 1 2 EQ
+            2 1 0 2 3 XOR
+            2 1 1 2 4 XOR
+        ";
+        let roles = BristolRoles {
+            inputs: vec![vec![0, 1]],
+            outputs: vec![vec![3, 4]],
+            randoms: vec![],
+        };
+        let circ = parse_bristol(text, &roles).unwrap();
+        assert_eq!(circ.vars[2].src, VarSrc::Const(true));
+        assert!(parse_bristol(&text.replace("1 2 EQ", "2 2 EQ"), &roles).is_err());
+    }
+}
@@ -0,0 +1,283 @@
+// STRAPS - Statistical Testing of RAndom Probing Security
+// Copyright (C) 2021 UCLouvain
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Import of gadgets written in the IronMask input language.
+//!
+//! A gadget description looks like
+//! ```text
+//! #SHARES 2
+//! #IN a b
+//! #RANDOMS r0
+//! #OUT c
+//! c0 = a0 * b0
+//! c0 = c0 + r0
+//! t = ! c0
+//! c1 = t
+//! ```
+//! The shares of a sharing `x` are named `x0`, `x1`, ... Variables may be assigned several
+//! times: each assignment creates a new variable, and the last value assigned to an output share
+//! is the output of the gadget. Copies (`x = y`) do not create any variable.
+
+use super::import_sl_sc::new_sl_sc;
+use super::sl_sc::{SlSharedCircuit, Var, VarSrc};
+use super::var_set::VarIdx;
+use std::collections::{HashMap, HashSet};
+
+struct IronMaskParser {
+    n_shares: Option<usize>,
+    inputs: Vec<String>,
+    outputs: Vec<String>,
+    out_line: usize,
+    vars: Vec<Var>,
+    // current variable bound to each IronMask name
+    bindings: HashMap<String, VarIdx>,
+    // all names of the circuit variables
+    used_names: HashSet<String>,
+}
+
+impl IronMaskParser {
+    fn new() -> Self {
+        Self {
+            n_shares: None,
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            out_line: 0,
+            vars: Vec::new(),
+            bindings: HashMap::new(),
+            used_names: HashSet::new(),
+        }
+    }
+
+    /// Name of the circuit variable for a new assignment of `name`: re-assigned names get a
+    /// `.k` suffix, which cannot clash with IronMask identifiers.
+    fn fresh_name(&mut self, name: &str) -> String {
+        let mut res = name.to_owned();
+        let mut k = 1;
+        while self.used_names.contains(&res) {
+            res = format!("{}.{}", name, k);
+            k += 1;
+        }
+        self.used_names.insert(res.clone());
+        res
+    }
+
+    fn new_var(&mut self, name: &str, src: VarSrc) -> VarIdx {
+        let var_name = self.fresh_name(name);
+        self.vars.push(Var {
+            src,
+            output_port: None,
+            name: var_name,
+        });
+        let idx = self.vars.len() - 1;
+        self.bindings.insert(name.to_owned(), idx);
+        idx
+    }
+
+    fn operand(&self, name: &str) -> Result<VarIdx, String> {
+        self.bindings
+            .get(name)
+            .copied()
+            .ok_or_else(|| format!("undefined variable '{}'", name))
+    }
+
+    fn n_shares(&self, directive: &str) -> Result<usize, String> {
+        self.n_shares
+            .ok_or_else(|| format!("{} must come after #SHARES", directive))
+    }
+
+    fn directive(&mut self, line_no: usize, tokens: &[&str]) -> Result<(), String> {
+        match tokens[0].to_uppercase().as_str() {
+            "#SHARES" => {
+                if self.n_shares.is_some() {
+                    return Err("duplicate #SHARES".to_owned());
+                }
+                let n_shares = match tokens {
+                    [_, n] => n.parse::<usize>().ok().filter(|n| *n > 0),
+                    _ => None,
+                };
+                self.n_shares =
+                    Some(n_shares.ok_or("#SHARES expects a positive number of shares")?);
+            }
+            "#IN" => {
+                let n_shares = self.n_shares("#IN")?;
+                for name in tokens[1..].iter() {
+                    let port = self.inputs.len();
+                    self.inputs.push((*name).to_owned());
+                    for share in 0..n_shares {
+                        self.new_var(&format!("{}{}", name, share), VarSrc::Input(port, share));
+                    }
+                }
+            }
+            "#RANDOMS" => {
+                for name in tokens[1..].iter() {
+                    if self.bindings.contains_key(*name) {
+                        return Err(format!("random '{}' is already defined", name));
+                    }
+                    self.new_var(name, VarSrc::Random);
+                }
+            }
+            "#OUT" => {
+                self.outputs
+                    .extend(tokens[1..].iter().map(|s| (*s).to_owned()));
+                self.out_line = line_no;
+            }
+            d => {
+                return Err(format!("unknown directive '{}'", d));
+            }
+        }
+        Ok(())
+    }
+
+    fn assignment(&mut self, tokens: &[&str]) -> Result<(), String> {
+        match tokens {
+            [dest, "=", op] => {
+                let op = self.operand(op)?;
+                self.bindings.insert((*dest).to_owned(), op);
+            }
+            [dest, "=", "!", op] => {
+                let op = self.operand(op)?;
+                self.new_var(dest, VarSrc::Not([op]));
+            }
+            [dest, "=", op1, operator @ ("+" | "*"), op2] => {
+                let mut ops = vec![self.operand(op1)?, self.operand(op2)?];
+                ops.sort_unstable();
+                let src = if *operator == "+" {
+                    VarSrc::Sum(ops)
+                } else {
+                    VarSrc::Product(ops)
+                };
+                self.new_var(dest, src);
+            }
+            _ => {
+                return Err("expected 'x = y', 'x = ! y', 'x = y + z' or 'x = y * z'".to_owned());
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<SlSharedCircuit, String> {
+        if self.outputs.is_empty() {
+            return Err("missing #OUT directive".to_owned());
+        }
+        let out_line = self.out_line;
+        self.bind_outputs()
+            .and_then(|n_shares| {
+                new_sl_sc(self.vars, n_shares, self.inputs.len(), self.outputs.len())
+            })
+            .map_err(|e| format!("line {}: {}", out_line, e))
+    }
+
+    fn bind_outputs(&mut self) -> Result<usize, String> {
+        let n_shares = self.n_shares("#OUT")?;
+        for (port, name) in self.outputs.iter().enumerate() {
+            for share in 0..n_shares {
+                let share_name = format!("{}{}", name, share);
+                let var =
+                    self.bindings.get(&share_name).copied().ok_or_else(|| {
+                        format!("output share '{}' is never assigned", share_name)
+                    })?;
+                if let Some((p, s)) = self.vars[var].output_port {
+                    return Err(format!(
+                        "output shares '{}{}' and '{}' have the same value",
+                        self.outputs[p], s, share_name
+                    ));
+                }
+                self.vars[var].output_port = Some((port, share));
+            }
+        }
+        Ok(n_shares)
+    }
+}
+
+/// Build a circuit from the text of an IronMask gadget description.
+///
+/// Errors are reported with the number of the offending line.
+pub fn parse_ironmask(text: &str) -> Result<SlSharedCircuit, String> {
+    let mut parser = IronMaskParser::new();
+    for (i, line) in text.lines().enumerate() {
+        let line_no = i + 1;
+        // Operators are not necessarily surrounded by spaces.
+        let line = line
+            .replace('=', " = ")
+            .replace('+', " + ")
+            .replace('*', " * ")
+            .replace('!', " ! ");
+        let tokens = line.split_whitespace().collect::<Vec<_>>();
+        let res = if tokens.is_empty() {
+            Ok(())
+        } else if tokens[0].starts_with('#') {
+            parser.directive(line_no, &tokens)
+        } else {
+            parser.assignment(&tokens)
+        };
+        res.map_err(|e| format!("line {}: {}", line_no, e))?;
+    }
+    parser.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ISW_2: &str = "
+        #SHARES 2
+        #IN a b
+        #RANDOMS r0
+        #OUT c
+        c0 = a0 * b0
+        c1 = a1 * b1
+        t0 = a0 * b1
+        t0 = t0 + r0
+        t1 = a1 * b0
+        t1 = t1 + t0
+        c0 = c0 + r0
+        c1 = c1 + t1
+    ";
+
+    #[test]
+    fn test_parse_isw() {
+        let circ = parse_ironmask(ISW_2).unwrap();
+        assert_eq!(circ.n_shares, 2);
+        assert_eq!(circ.n_input_ports, 2);
+        assert_eq!(circ.n_output_ports, 1);
+        // 4 input shares, 1 random, 8 assignments
+        assert_eq!(circ.vars.len(), 13);
+        assert_eq!(circ.vars[11].name, "c0.1");
+        assert_eq!(circ.vars[11].output_port, Some((0, 0)));
+        assert_eq!(circ.vars[12].output_port, Some((0, 1)));
+    }
+
+    #[test]
+    fn test_parse_copy_not() {
+        let circ = parse_ironmask("#SHARES 1\n#IN a\n#OUT b\nt=!a0\nb0=t").unwrap();
+        assert_eq!(circ.vars.len(), 2);
+        assert_eq!(circ.vars[1].src, VarSrc::Not([0]));
+        assert_eq!(circ.vars[1].output_port, Some((0, 0)));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let err = parse_ironmask("#SHARES 1\n#IN a\n#OUT b\nb0 = a0 + x").unwrap_err();
+        assert_eq!(err, "line 4: undefined variable 'x'");
+        let err = parse_ironmask("#IN a\n#SHARES 1").unwrap_err();
+        assert_eq!(err, "line 1: #IN must come after #SHARES");
+        let err = parse_ironmask("#SHARES 2\n#IN a\n#OUT b\nb0 = a0\nb1 = a0").unwrap_err();
+        assert_eq!(
+            err,
+            "line 3: output shares 'b0' and 'b1' have the same value"
+        );
+    }
+}
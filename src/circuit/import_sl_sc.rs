@@ -81,6 +81,11 @@ pub fn new_sl_sc(
                 return Err(format!("Invalid op sorting, {} {}", i, op));
             }
         }
+        if let VarSrc::Sum(ops) | VarSrc::Product(ops) = &v.src {
+            if ops.len() < 2 {
+                return Err(format!("Gate {} has less than 2 operands", i));
+            }
+        }
     }
     // check completeness and unicity of (port, idx) pairs for inputs and outputs
     check_completeness_sharings(&vars, n_shares, n_input_ports, "input", |v| {
@@ -0,0 +1,756 @@
+// STRAPS - Statistical Testing of RAndom Probing Security
+// Copyright (C) 2021 UCLouvain
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Import of gate-level structural Verilog.
+//!
+//! A single module is supported, made of primitive gate instances (`and`, `or`, `xor`, `nand`,
+//! `nor`, `xnor`, `not`, `buf`) and `assign` statements with `~`, `&`, `^`, `|` expressions.
+//! Bits of vectors are named `a[i]`.
+//!
+//! When no [`PortSpec`] is given, the roles of the module ports are inferred:
+//! * if any port has a `share` attribute, all ports must be annotated: `(* share = "p,s" *)`
+//!   makes a scalar port the share `s` of the sharing `p`, `(* share = "p" *)` makes bit `i` of a
+//!   vector port the share `i` of sharing `p`, and `(* random *)` marks random inputs;
+//! * otherwise, naming conventions are used: inputs named `r`, `rnd*` or `rand*` (with an optional
+//!   index) are randoms, vector ports are sharings (bit `i` is share `i`), and scalar ports
+//!   `<base><i>` or `<base>_<i>` are the share `i` of the sharing `<base>`. Sharings are numbered
+//!   in declaration order.
+
+use super::aig::{Aig, Lit, LIT_FALSE};
+use super::port_spec::PortSpec;
+use super::sl_sc::SlSharedCircuit;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone)]
+struct Tok {
+    s: String,
+    line: usize,
+}
+
+fn tokenize(text: &str) -> Result<Vec<Tok>, String> {
+    let chars = text.chars().collect::<Vec<_>>();
+    let mut toks = Vec::new();
+    let mut line = 1;
+    let mut i = 0;
+    let is_ident = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '$';
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        let start = i;
+        match (c, next) {
+            ('\n', _) => {
+                line += 1;
+                i += 1;
+                continue;
+            }
+            (c, _) if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            ('/', Some('/')) => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+                continue;
+            }
+            ('/', Some('*')) => {
+                i += 2;
+                while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                    line += (chars[i] == '\n') as usize;
+                    i += 1;
+                }
+                i += 2;
+                continue;
+            }
+            ('(', Some('*')) if chars.get(i + 2) != Some(&')') => i += 2,
+            ('*', Some(')')) => i += 2,
+            ('"', _) => {
+                i += 1;
+                while i < chars.len() && chars[i] != '"' {
+                    i += 1;
+                }
+                if i == chars.len() {
+                    return Err(format!("line {}: unterminated string", line));
+                }
+                i += 1;
+            }
+            ('\\', _) => {
+                // Escaped identifier, the backslash is not part of the name.
+                i += 1;
+                while i < chars.len() && !chars[i].is_whitespace() {
+                    i += 1;
+                }
+                toks.push(Tok {
+                    s: chars[start + 1..i].iter().collect(),
+                    line,
+                });
+                continue;
+            }
+            (c, _) if c.is_ascii_digit() => {
+                while i < chars.len() && (is_ident(chars[i]) || chars[i] == '\'') {
+                    i += 1;
+                }
+            }
+            (c, _) if is_ident(c) => {
+                while i < chars.len() && is_ident(chars[i]) {
+                    i += 1;
+                }
+            }
+            (c, _) if "()[]:;,=~&|^!#".contains(c) => i += 1,
+            (c, _) => return Err(format!("line {}: unexpected character '{}'", line, c)),
+        }
+        toks.push(Tok {
+            s: chars[start..i].iter().collect(),
+            line,
+        });
+    }
+    Ok(toks)
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Sig(String),
+    Const(bool),
+    Not(Box<Expr>),
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+    Xor(Vec<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Dir {
+    Input,
+    Output,
+    Wire,
+}
+
+type Attrs = Vec<(String, Option<String>)>;
+
+#[derive(Debug, Clone)]
+struct Decl {
+    dir: Dir,
+    name: String,
+    /// Bit range (lsb, msb) of vectors.
+    range: Option<(usize, usize)>,
+    attrs: Attrs,
+    line: usize,
+}
+
+impl Decl {
+    fn bits(&self) -> Vec<String> {
+        match self.range {
+            None => vec![self.name.clone()],
+            Some((lo, hi)) => (lo..=hi).map(|i| format!("{}[{}]", self.name, i)).collect(),
+        }
+    }
+    fn attr(&self, key: &str) -> Option<Option<&str>> {
+        self.attrs
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_deref())
+    }
+}
+
+const GATES: [&str; 8] = ["and", "or", "xor", "nand", "nor", "xnor", "not", "buf"];
+
+#[derive(Debug, Default)]
+struct Module {
+    decls: Vec<Decl>,
+    /// Driver of each signal, with its line.
+    defs: HashMap<String, (usize, Expr)>,
+}
+
+struct Parser {
+    toks: Vec<Tok>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&str> {
+        self.toks.get(self.pos).map(|t| t.s.as_str())
+    }
+    fn line(&self) -> usize {
+        self.toks
+            .get(self.pos)
+            .or_else(|| self.toks.last())
+            .map(|t| t.line)
+            .unwrap_or(0)
+    }
+    fn err(&self, msg: impl std::fmt::Display) -> String {
+        format!("line {}: {}", self.line(), msg)
+    }
+    fn next(&mut self) -> Result<String, String> {
+        let tok = self
+            .toks
+            .get(self.pos)
+            .ok_or_else(|| self.err("unexpected end of file"))?;
+        self.pos += 1;
+        Ok(tok.s.clone())
+    }
+    fn eat(&mut self, s: &str) -> bool {
+        if self.peek() == Some(s) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+    fn expect(&mut self, s: &str) -> Result<(), String> {
+        if self.eat(s) {
+            Ok(())
+        } else {
+            Err(self.err(format!("expected '{}'", s)))
+        }
+    }
+    fn ident(&mut self) -> Result<String, String> {
+        match self.peek() {
+            Some(s)
+                if s.starts_with(|c: char| {
+                    !c.is_ascii_digit() && !"()[]:;,=~&|^!#*\"".contains(c)
+                }) =>
+            {
+                self.next()
+            }
+            _ => Err(self.err("expected an identifier")),
+        }
+    }
+    fn number(&mut self) -> Result<usize, String> {
+        let s = self.next()?;
+        s.parse::<usize>()
+            .map_err(|_| format!("line {}: invalid number '{}'", self.line(), s))
+    }
+
+    /// Attributes `(* key = value, ... *)`, possibly several lists.
+    fn attrs(&mut self) -> Result<Attrs, String> {
+        let mut res = Vec::new();
+        while self.eat("(*") {
+            loop {
+                let key = self.ident()?;
+                let value = if self.eat("=") {
+                    Some(self.next()?.trim_matches('"').to_owned())
+                } else {
+                    None
+                };
+                res.push((key, value));
+                if !self.eat(",") {
+                    break;
+                }
+            }
+            self.expect("*)")?;
+        }
+        Ok(res)
+    }
+
+    /// Optional `[msb:lsb]` range.
+    fn range(&mut self) -> Result<Option<(usize, usize)>, String> {
+        if !self.eat("[") {
+            return Ok(None);
+        }
+        let a = self.number()?;
+        self.expect(":")?;
+        let b = self.number()?;
+        self.expect("]")?;
+        Ok(Some((a.min(b), a.max(b))))
+    }
+
+    /// Signal name, with optional bit select.
+    fn signal(&mut self) -> Result<String, String> {
+        let name = self.ident()?;
+        if self.eat("[") {
+            let bit = self.number()?;
+            self.expect("]")?;
+            Ok(format!("{}[{}]", name, bit))
+        } else {
+            Ok(name)
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr, String> {
+        self.binary(0)
+    }
+
+    /// Binary operators by increasing precedence: `|`, `^`, `&`.
+    fn binary(&mut self, level: usize) -> Result<Expr, String> {
+        const OPS: [&str; 3] = ["|", "^", "&"];
+        if level == OPS.len() {
+            return self.unary();
+        }
+        let mut operands = vec![self.binary(level + 1)?];
+        while self.eat(OPS[level]) {
+            operands.push(self.binary(level + 1)?);
+        }
+        Ok(if operands.len() == 1 {
+            operands.pop().unwrap()
+        } else {
+            match level {
+                0 => Expr::Or(operands),
+                1 => Expr::Xor(operands),
+                _ => Expr::And(operands),
+            }
+        })
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.eat("~") || self.eat("!") {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        if self.eat("(") {
+            let res = self.expr()?;
+            self.expect(")")?;
+            return Ok(res);
+        }
+        match self.peek() {
+            Some(s) if s.starts_with(|c: char| c.is_ascii_digit()) => {
+                let s = self.next()?;
+                // Value after the base of sized constants such as 1'b0
+                match s.rsplit(['\'', 'b', 'h', 'd']).next() {
+                    Some("0") => Ok(Expr::Const(false)),
+                    Some("1") => Ok(Expr::Const(true)),
+                    _ => Err(self.err(format!("unsupported constant '{}'", s))),
+                }
+            }
+            _ => Ok(Expr::Sig(self.signal()?)),
+        }
+    }
+
+    /// Names declared after a direction keyword, up to `;` (or `,`/`)` in port lists).
+    fn decl(
+        &mut self,
+        dir: Dir,
+        attrs: Attrs,
+        module: &mut Module,
+        in_port_list: bool,
+    ) -> Result<(), String> {
+        self.eat("wire");
+        let range = self.range()?;
+        loop {
+            let line = self.line();
+            let name = self.ident()?;
+            if !in_port_list && self.eat("=") {
+                let expr = self.expr()?;
+                module.define(name.clone(), line, expr)?;
+            }
+            module.decls.push(Decl {
+                dir,
+                name,
+                range,
+                attrs: attrs.clone(),
+                line,
+            });
+            // In ANSI port lists, a comma may be followed by a new declaration.
+            if in_port_list
+                && matches!(
+                    self.toks.get(self.pos + 1).map(|t| t.s.as_str()),
+                    Some("input") | Some("output") | Some("(*")
+                )
+            {
+                return Ok(());
+            }
+            if !self.eat(",") {
+                return Ok(());
+            }
+        }
+    }
+
+    fn module(&mut self) -> Result<Module, String> {
+        let mut module = Module::default();
+        self.attrs()?;
+        self.expect("module")?;
+        self.ident()?;
+        if self.eat("(") {
+            while !self.eat(")") {
+                let attrs = self.attrs()?;
+                match self.peek() {
+                    Some("input") | Some("output") => {
+                        let dir = if self.next()? == "input" {
+                            Dir::Input
+                        } else {
+                            Dir::Output
+                        };
+                        self.decl(dir, attrs, &mut module, true)?;
+                    }
+                    // Non-ANSI port list, ports are declared in the body.
+                    _ => {
+                        self.ident()?;
+                    }
+                }
+                if self.peek() != Some(")") {
+                    self.expect(",")?;
+                }
+            }
+        }
+        self.expect(";")?;
+        loop {
+            let attrs = self.attrs()?;
+            let kw = self.next()?;
+            match kw.as_str() {
+                "endmodule" => break,
+                "input" | "output" | "wire" => {
+                    let dir = match kw.as_str() {
+                        "input" => Dir::Input,
+                        "output" => Dir::Output,
+                        _ => Dir::Wire,
+                    };
+                    self.decl(dir, attrs, &mut module, false)?;
+                }
+                "assign" => loop {
+                    let line = self.line();
+                    let lhs = self.signal()?;
+                    self.expect("=")?;
+                    let expr = self.expr()?;
+                    module.define(lhs, line, expr)?;
+                    if !self.eat(",") {
+                        break;
+                    }
+                },
+                gate if GATES.contains(&gate) => loop {
+                    if self.peek() != Some("(") {
+                        self.ident()?;
+                    }
+                    let line = self.line();
+                    self.expect("(")?;
+                    let out = self.signal()?;
+                    let mut ins = Vec::new();
+                    while self.eat(",") {
+                        ins.push(self.expr()?);
+                    }
+                    self.expect(")")?;
+                    let expr = match (gate, ins.len()) {
+                        ("not", 1) => Expr::Not(Box::new(ins.pop().unwrap())),
+                        ("buf", 1) => ins.pop().unwrap(),
+                        ("not", _) | ("buf", _) => {
+                            return Err(format!("line {}: {} gate with several inputs", line, gate))
+                        }
+                        (_, n) if n < 2 => {
+                            return Err(format!(
+                                "line {}: {} gate with less than 2 inputs",
+                                line, gate
+                            ))
+                        }
+                        ("and", _) => Expr::And(ins),
+                        ("or", _) => Expr::Or(ins),
+                        ("xor", _) => Expr::Xor(ins),
+                        ("nand", _) => Expr::Not(Box::new(Expr::And(ins))),
+                        ("nor", _) => Expr::Not(Box::new(Expr::Or(ins))),
+                        _ => Expr::Not(Box::new(Expr::Xor(ins))),
+                    };
+                    module.define(out, line, expr)?;
+                    if !self.eat(",") {
+                        break;
+                    }
+                },
+                _ => {
+                    self.pos -= 1;
+                    return Err(self.err(format!("unsupported statement '{}'", kw)));
+                }
+            }
+            self.expect(";")?;
+        }
+        Ok(module)
+    }
+}
+
+impl Module {
+    fn define(&mut self, name: String, line: usize, expr: Expr) -> Result<(), String> {
+        if self.defs.insert(name.clone(), (line, expr)).is_some() {
+            return Err(format!(
+                "line {}: signal '{}' has several drivers",
+                line, name
+            ));
+        }
+        Ok(())
+    }
+
+    /// Ports of each role, following attributes or naming conventions.
+    fn port_spec(&self) -> Result<PortSpec, String> {
+        let mut inputs: Vec<Vec<Option<String>>> = Vec::new();
+        let mut outputs: Vec<Vec<Option<String>>> = Vec::new();
+        let mut randoms = Vec::new();
+        // port index of each sharing name, for naming conventions
+        let mut sharings: HashMap<(bool, String), usize> = HashMap::new();
+        let use_attrs = self.decls.iter().any(|d| d.attr("share").is_some());
+        for decl in self.decls.iter().filter(|d| d.dir != Dir::Wire) {
+            let is_input = decl.dir == Dir::Input;
+            let err = |msg: &str| format!("line {}: port '{}' {}", decl.line, decl.name, msg);
+            let bits = decl.bits();
+            if is_input
+                && ((use_attrs && decl.attr("random").is_some())
+                    || (!use_attrs && is_random_name(&decl.name)))
+            {
+                randoms.extend(bits);
+                continue;
+            }
+            // (port, first share) of the declaration
+            let (port, share) = if use_attrs {
+                let share = decl
+                    .attr("share")
+                    .flatten()
+                    .ok_or_else(|| err("has no share attribute"))?;
+                let nums = share
+                    .split(',')
+                    .map(|x| x.trim().parse::<usize>())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| err("has an invalid share attribute"))?;
+                match (nums.as_slice(), decl.range) {
+                    ([port], Some(_)) => (*port, 0),
+                    ([port, share], None) => (*port, *share),
+                    _ => return Err(err("has an invalid share attribute")),
+                }
+            } else {
+                let (base, share) = match decl.range {
+                    Some(_) => (decl.name.as_str(), 0),
+                    None => split_share(&decl.name).ok_or_else(|| err("has no share index"))?,
+                };
+                let n_sharings = sharings.iter().filter(|((i, _), _)| *i == is_input).count();
+                let port = *sharings
+                    .entry((is_input, base.to_owned()))
+                    .or_insert(n_sharings);
+                (port, share)
+            };
+            let ports = if is_input { &mut inputs } else { &mut outputs };
+            for (i, bit) in bits.into_iter().enumerate() {
+                place(ports, port, share + i, bit)?;
+            }
+        }
+        let collect = |ports: Vec<Vec<Option<String>>>| {
+            ports
+                .into_iter()
+                .enumerate()
+                .map(|(port, sharing)| {
+                    sharing
+                        .into_iter()
+                        .enumerate()
+                        .map(|(share, name)| {
+                            name.ok_or_else(|| {
+                                format!("share {} of sharing {} is missing", share, port)
+                            })
+                        })
+                        .collect::<Result<Vec<_>, _>>()
+                })
+                .collect::<Result<Vec<_>, _>>()
+        };
+        PortSpec::new(collect(inputs)?, collect(outputs)?, randoms)
+    }
+}
+
+fn place(
+    ports: &mut Vec<Vec<Option<String>>>,
+    port: usize,
+    share: usize,
+    name: String,
+) -> Result<(), String> {
+    if ports.len() <= port {
+        ports.resize(port + 1, Vec::new());
+    }
+    let sharing = &mut ports[port];
+    if sharing.len() <= share {
+        sharing.resize(share + 1, None);
+    }
+    if let Some(other) = &sharing[share] {
+        return Err(format!(
+            "'{}' and '{}' are both share {} of sharing {}",
+            other, name, share, port
+        ));
+    }
+    sharing[share] = Some(name);
+    Ok(())
+}
+
+/// Split `<base><i>` or `<base>_<i>` into base and share index.
+fn split_share(name: &str) -> Option<(&str, usize)> {
+    let base = name.trim_end_matches(|c: char| c.is_ascii_digit());
+    let share = name[base.len()..].parse::<usize>().ok()?;
+    let base = base.strip_suffix('_').unwrap_or(base);
+    if base.is_empty() {
+        None
+    } else {
+        Some((base, share))
+    }
+}
+
+fn is_random_name(name: &str) -> bool {
+    let base = split_share(name).map(|(base, _)| base).unwrap_or(name);
+    base == "r" || base.starts_with("rnd") || base.starts_with("rand")
+}
+
+struct ExprBuilder {
+    aig: Aig,
+    defs: HashMap<String, (usize, Expr)>,
+    signals: HashMap<String, Lit>,
+    // signals being built, to detect loops
+    pending: Vec<String>,
+}
+
+impl ExprBuilder {
+    fn signal(&mut self, name: &str) -> Result<Lit, String> {
+        if let Some(lit) = self.signals.get(name) {
+            return Ok(*lit);
+        }
+        if self.pending.iter().any(|s| s == name) {
+            return Err(format!("combinational loop through '{}'", name));
+        }
+        let (_, expr) = self
+            .defs
+            .remove(name)
+            .ok_or_else(|| format!("signal '{}' is not driven", name))?;
+        self.pending.push(name.to_owned());
+        let lit = self.expr(&expr)?;
+        self.pending.pop();
+        self.aig.set_name(lit, name.to_owned());
+        self.signals.insert(name.to_owned(), lit);
+        Ok(lit)
+    }
+
+    fn expr(&mut self, expr: &Expr) -> Result<Lit, String> {
+        Ok(match expr {
+            Expr::Sig(name) => self.signal(name)?,
+            Expr::Const(value) => LIT_FALSE ^ (*value as Lit),
+            Expr::Not(e) => self.expr(e)? ^ 1,
+            Expr::And(es) | Expr::Or(es) | Expr::Xor(es) => {
+                let lits = es
+                    .iter()
+                    .map(|e| self.expr(e))
+                    .collect::<Result<Vec<_>, _>>()?;
+                match expr {
+                    Expr::And(_) => self.aig.and_many(lits),
+                    Expr::Or(_) => self.aig.or_many(lits),
+                    _ => {
+                        let (first, rest) = lits.split_first().unwrap();
+                        rest.iter().fold(*first, |acc, l| self.aig.add_xor(acc, *l))
+                    }
+                }
+            }
+        })
+    }
+}
+
+/// Build a circuit from a structural Verilog module.
+///
+/// The roles of the ports are given by `spec`, or inferred from attributes and names.
+pub fn parse_verilog(text: &str, spec: Option<&PortSpec>) -> Result<SlSharedCircuit, String> {
+    let mut parser = Parser {
+        toks: tokenize(text)?,
+        pos: 0,
+    };
+    let module = parser.module()?;
+    let spec = match spec {
+        Some(spec) => spec.clone(),
+        None => module.port_spec()?,
+    };
+    let mut builder = ExprBuilder {
+        aig: Aig::new(),
+        defs: module.defs,
+        signals: HashMap::new(),
+        pending: Vec::new(),
+    };
+    for decl in module.decls.iter().filter(|d| d.dir == Dir::Input) {
+        for bit in decl.bits() {
+            let lit = builder.aig.add_input(bit.clone());
+            builder.signals.insert(bit, lit);
+        }
+    }
+    for decl in module.decls.iter().filter(|d| d.dir == Dir::Output) {
+        for bit in decl.bits() {
+            let lit = builder
+                .signal(&bit)
+                .map_err(|e| format!("line {}: {}", decl.line, e))?;
+            builder.aig.set_output(bit, lit);
+        }
+    }
+    builder.aig.to_circuit(&spec)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::VarSrc;
+
+    // First-order DOM-indep multiplication.
+    const DOM: &str = "
+        // c = a & b
+        module dom_and(a, b, r0, c);
+            input [1:0] a, b;
+            input r0;
+            output [1:0] c;
+            wire p00, p01, p10, p11, t01, t10;
+            and g0 (p00, a[0], b[0]), g1 (p11, a[1], b[1]);
+            and (p01, a[0], b[1]);
+            assign p10 = a[1] & b[0];
+            xor (t01, p01, r0);
+            assign t10 = p10 ^ r0, c[0] = p00 ^ t01;
+            assign c[1] = p11 ^ t10;
+        endmodule
+    ";
+
+    #[test]
+    fn test_parse_conventions() {
+        let circ = parse_verilog(DOM, None).unwrap();
+        assert_eq!(circ.n_shares, 2);
+        assert_eq!(circ.n_input_ports, 2);
+        let srcs = circ.vars.iter().map(|v| v.src.clone()).collect::<Vec<_>>();
+        assert_eq!(
+            srcs[..5],
+            [
+                VarSrc::Input(0, 0),
+                VarSrc::Input(0, 1),
+                VarSrc::Input(1, 0),
+                VarSrc::Input(1, 1),
+                VarSrc::Random,
+            ]
+        );
+        let n_products = srcs
+            .iter()
+            .filter(|s| matches!(s, VarSrc::Product(_)))
+            .count();
+        let n_sums = srcs.iter().filter(|s| matches!(s, VarSrc::Sum(_))).count();
+        assert_eq!((n_products, n_sums, srcs.len()), (4, 4, 13));
+        let c1 = circ.vars.iter().find(|v| v.name == "c[1]").unwrap();
+        assert_eq!(c1.output_port, Some((0, 1)));
+    }
+
+    #[test]
+    fn test_parse_attributes() {
+        let text = r#"
+            module refresh (
+                (* share = "0,1" *) input x1,
+                (* share = "0,0" *) input x0,
+                (* random *) input m,
+                (* share = "0" *) output [1:0] y
+            );
+                assign y[0] = x0 ^ m;
+                xnor (y[1], x1, m);
+            endmodule
+        "#;
+        let circ = parse_verilog(text, None).unwrap();
+        let srcs = circ.vars.iter().map(|v| v.src.clone()).collect::<Vec<_>>();
+        assert_eq!(
+            srcs,
+            vec![
+                VarSrc::Input(0, 0),
+                VarSrc::Input(0, 1),
+                VarSrc::Random,
+                VarSrc::Sum(vec![0, 2]),
+                VarSrc::Sum(vec![1, 2]),
+                VarSrc::Not([4]),
+            ]
+        );
+        let text = text.replace("(* random *) ", "");
+        assert_eq!(
+            parse_verilog(&text, None).unwrap_err(),
+            "line 5: port 'm' has no share attribute"
+        );
+    }
+}
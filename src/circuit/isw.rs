@@ -14,7 +14,9 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use super::{new_sl_sc, SlSharedCircuit, Var, VarSrc};
+use super::{
+    new_sl_sc, Ring, Sharing, SlSharedCircuit, Var, VarSrc, WordCircuit, WordSrc, WordVar,
+};
 
 fn collect_single<T>(n: usize, f: impl FnMut(usize) -> T) -> Vec<T> {
     (0..n).map(f).collect()
@@ -52,14 +54,14 @@ pub fn build_isw(n: usize) -> SlSharedCircuit {
     });
     if n == 1 {
         new_var(Var {
-            src: VarSrc::Product([input_x[0], input_y[0]]),
+            src: VarSrc::Product(vec![input_x[0], input_y[0]]),
             output_port: Some((0, 0)),
             name: "o_0".to_owned(),
         });
     } else {
         let products = collect_all(n, |i, j| {
             new_var(Var {
-                src: VarSrc::Product([input_x[i], input_y[j]]),
+                src: VarSrc::Product(vec![input_x[i], input_y[j]]),
                 output_port: None,
                 name: format!("p_{}_{}", i, j),
             })
@@ -69,7 +71,7 @@ pub fn build_isw(n: usize) -> SlSharedCircuit {
                 products[i][j]
             } else {
                 new_var(Var {
-                    src: VarSrc::Sum([
+                    src: VarSrc::Sum(vec![
                         products[i][j],
                         if i < j { randoms[j][i] } else { randoms[i][j] },
                     ]),
@@ -81,7 +83,7 @@ pub fn build_isw(n: usize) -> SlSharedCircuit {
         let _cumsums = collect_single(n, |i| {
             (1..n).fold(ref_prod[i][0], |acc, j| {
                 new_var(Var {
-                    src: VarSrc::Sum([acc, ref_prod[i][j]]),
+                    src: VarSrc::Sum(vec![acc, ref_prod[i][j]]),
                     output_port: if j == n - 1 { Some((0, i)) } else { None },
                     name: format!("c_{}_{}", i, j),
                 })
@@ -91,3 +93,88 @@ pub fn build_isw(n: usize) -> SlSharedCircuit {
     let circuit = new_sl_sc(vars, n, 2, 1).expect("Bad ISW");
     return circuit;
 }
+
+/// ISW multiplication of arithmetic sharings in `ring`, with `n` shares.
+pub fn build_word_isw(ring: Ring, n: usize) -> WordCircuit {
+    let mut vars = vec![];
+    let mut new_var = |src, output_port, name: String| {
+        vars.push(WordVar {
+            src,
+            output_port,
+            name,
+        });
+        vars.len() - 1
+    };
+    let input_x = collect_single(n, |i| {
+        new_var(WordSrc::Input(0, i), None, format!("x_{}", i))
+    });
+    let input_y = collect_single(n, |i| {
+        new_var(WordSrc::Input(1, i), None, format!("y_{}", i))
+    });
+    // r_i_j for i < j
+    let randoms = collect_all(n, |i, j| {
+        (i < j).then(|| new_var(WordSrc::Random, None, format!("r_{}_{}", i, j)))
+    });
+    let products = collect_all(n, |i, j| {
+        let output_port = if n == 1 { Some((0, 0)) } else { None };
+        new_var(
+            WordSrc::MulMod([input_x[i], input_y[j]]),
+            output_port,
+            format!("p_{}_{}", i, j),
+        )
+    });
+    // For i < j, t_i_j = r_i_j and t_j_i = (p_i_j - r_i_j) + p_j_i.
+    let mut terms = collect_all(n, |i, j| randoms[i][j].unwrap_or(products[i][j]));
+    for i in 0..n {
+        for j in i + 1..n {
+            let u = new_var(
+                WordSrc::SubMod([products[i][j], randoms[i][j].unwrap()]),
+                None,
+                format!("u_{}_{}", i, j),
+            );
+            terms[j][i] = new_var(
+                WordSrc::AddMod([u, products[j][i]]),
+                None,
+                format!("t_{}_{}", j, i),
+            );
+        }
+    }
+    for (i, terms) in terms.iter().enumerate() {
+        (0..n).filter(|j| *j != i).fold(terms[i], |acc, j| {
+            let last = j == n - 1 || (i == n - 1 && j == n - 2);
+            new_var(
+                WordSrc::AddMod([acc, terms[j]]),
+                if last { Some((0, i)) } else { None },
+                format!("c_{}_{}", i, j),
+            )
+        });
+    }
+    let sharings = vec![Sharing::Arithmetic; 2];
+    WordCircuit::new(ring, vars, n, sharings, vec![Sharing::Arithmetic]).expect("Bad ISW")
+}
+
+/// Sharewise square of an arithmetic sharing in GF(2^n), with `n` shares.
+pub fn build_word_square(ring: Ring, n: usize) -> Result<WordCircuit, String> {
+    if !matches!(ring, Ring::Gf2n { .. }) {
+        return Err(format!("square is not linear in {:?}", ring));
+    }
+    let mut vars = (0..n)
+        .map(|i| WordVar {
+            src: WordSrc::Input(0, i),
+            output_port: None,
+            name: format!("x_{}", i),
+        })
+        .collect::<Vec<_>>();
+    vars.extend((0..n).map(|i| WordVar {
+        src: WordSrc::FieldSquare([i]),
+        output_port: Some((0, i)),
+        name: format!("s_{}", i),
+    }));
+    WordCircuit::new(
+        ring,
+        vars,
+        n,
+        vec![Sharing::Arithmetic],
+        vec![Sharing::Arithmetic],
+    )
+}
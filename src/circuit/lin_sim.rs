@@ -0,0 +1,113 @@
+// STRAPS - Statistical Testing of RAndom Probing Security
+// Copyright (C) 2021 UCLouvain
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Simulatability by Gaussian elimination of the randoms that appear linearly in the ANFs.
+//!
+//! Let the probes be `p_i = L_i(r) + N_i`, where `r` are the randoms that appear only in degree-1
+//! monomials of all the probes. An echelon form of the matrix of the `L_i` gives an invertible
+//! transform of the probes into rows that each contain a distinct pivot random (hence are
+//! uniform and independent of all other rows) and rows that do not depend on `r`. Only the latter
+//! have to be simulated, and the procedure is repeated on them since some randoms may now appear
+//! only linearly. Remaining rows are simulated from all the inputs they contain (sound fallback
+//! for nonlinear terms).
+
+use super::poly::{BitSet, Monomial, Polynomial};
+use super::var_set::VarIdx;
+
+/// Non-random variables on which the joint distribution of `polys` may depend.
+pub(crate) fn required_vars(
+    mut polys: Vec<Polynomial>,
+    is_random: impl Fn(VarIdx) -> bool,
+) -> Vec<VarIdx> {
+    loop {
+        let mut nonlinear = BitSet::default();
+        for mon in polys.iter().flat_map(|p| p.terms()) {
+            if mon.degree() > 1 {
+                nonlinear.union_with(mon.variable_set());
+            }
+        }
+        let first_linear_random = |p: &Polynomial| {
+            p.terms()
+                .filter(|mon| mon.degree() == 1)
+                .flat_map(|mon| mon.variables::<VarIdx>())
+                .find(|v| is_random(*v) && !nonlinear.contains(*v))
+        };
+        // (pivot random, row)
+        let mut pivots: Vec<(VarIdx, Polynomial)> = Vec::new();
+        let mut rest = Vec::new();
+        for mut poly in polys {
+            for (pivot, row) in pivots.iter() {
+                if poly.terms().any(|mon| *mon == Monomial::from_var(*pivot)) {
+                    poly += row;
+                }
+            }
+            match first_linear_random(&poly) {
+                Some(pivot) => pivots.push((pivot, poly)),
+                None => rest.push(poly),
+            }
+        }
+        polys = rest;
+        if pivots.is_empty() {
+            break;
+        }
+    }
+    let mut res = BitSet::default();
+    for poly in polys.iter() {
+        res.union_with(&poly.variables_set());
+    }
+    res.iter().filter(|v| !is_random(*v)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::circuit::{new_sl_sc, sim_set_with, SimMethod, Var, VarSrc};
+
+    #[test]
+    fn test_linalg_sim() {
+        // Probes a0 + r1 + r2, b0 + r1 + r3 and r1 + r2 + r3: every random is used by several
+        // probes, but the three probes are uniform and independent.
+        let vars = vec![
+            Var::new(VarSrc::Input(0, 0), None, "a0"),
+            Var::new(VarSrc::Input(0, 1), None, "a1"),
+            Var::new(VarSrc::Input(1, 0), None, "b0"),
+            Var::new(VarSrc::Input(1, 1), None, "b1"),
+            Var::new(VarSrc::Random, None, "r1"),
+            Var::new(VarSrc::Random, None, "r2"),
+            Var::new(VarSrc::Random, None, "r3"),
+            Var::new(VarSrc::Sum(vec![0, 4]), None, "t1"),
+            Var::new(VarSrc::Sum(vec![5, 7]), None, "p1"),
+            Var::new(VarSrc::Sum(vec![2, 4]), None, "t2"),
+            Var::new(VarSrc::Sum(vec![6, 9]), None, "p2"),
+            Var::new(VarSrc::Sum(vec![4, 5]), None, "t3"),
+            Var::new(VarSrc::Sum(vec![6, 11]), None, "p3"),
+            Var::new(VarSrc::Sum(vec![1, 3]), Some((0, 0)), "o0"),
+            Var::new(VarSrc::Not([12]), Some((0, 1)), "o1"),
+        ];
+        let circ = new_sl_sc(vars, 2, 2, 1).unwrap();
+        let probes = vec![8, 10, 12];
+        assert_eq!(
+            sim_set_with(&circ, probes.clone(), SimMethod::Heuristic),
+            vec![0, 2]
+        );
+        assert_eq!(
+            sim_set_with(&circ, probes.clone(), SimMethod::LinAlg),
+            Vec::<u32>::new()
+        );
+        // a1 + b1 is not masked, but does not make the other probes depend on a0 or b0.
+        let probes = vec![8, 10, 12, 13];
+        assert_eq!(sim_set_with(&circ, probes, SimMethod::LinAlg), vec![1, 3]);
+    }
+}
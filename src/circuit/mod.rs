@@ -14,16 +14,47 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+mod aig;
+mod brute_sim;
+mod dot;
+mod equivalence;
 mod es_graph;
+mod function;
+mod import_aiger;
+mod import_blif;
+mod import_bristol;
+mod import_ironmask;
 mod import_sl_sc;
+mod import_verilog;
 mod isw;
+mod lin_sim;
 mod ni;
+mod non_completeness;
 mod poly;
+mod port_spec;
 mod sl_sc;
+mod threshold;
+mod uniformity;
 mod utils;
 mod var_set;
+mod word;
+mod word_sim;
 
+pub use self::brute_sim::sim_set_exhaustive;
+pub use self::equivalence::{Distinguisher, Equivalence, PortMap};
+pub use self::function::{FunctionMismatch, FunctionSpec};
+pub use self::import_aiger::parse_aiger;
+pub use self::import_blif::parse_blif;
+pub use self::import_bristol::{parse_bristol, BristolRoles};
+pub use self::import_ironmask::parse_ironmask;
 pub use self::import_sl_sc::new_sl_sc;
-pub use self::isw::build_isw;
-pub use self::ni::sim_set;
+pub use self::import_verilog::parse_verilog;
+pub use self::isw::{build_isw, build_word_isw, build_word_square};
+pub use self::ni::{sim_set, sim_set_with, SimMethod};
+pub use self::non_completeness::NonCompletenessViolation;
+pub use self::port_spec::PortSpec;
 pub use self::sl_sc::{SlSharedCircuit, Var, VarSrc};
+pub use self::threshold::{check_probing, Counterexample, ProbingProperty};
+pub use self::uniformity::UniformityFailure;
+pub use self::word::{Ring, Sharing, WordCircuit, WordSrc, WordVar};
+pub use self::word_sim::WordSimMethod;
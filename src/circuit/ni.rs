@@ -17,14 +17,45 @@
 use super::es_graph::CompGraphWork;
 use super::sl_sc::SlSharedCircuit;
 
+/// Algorithm used to find the input shares required to simulate a set of probes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SimMethod {
+    /// Graph simplification heuristic.
+    Heuristic,
+    /// Heuristic, then Gaussian elimination of the randoms that appear linearly in the probes.
+    LinAlg,
+}
+
+impl std::str::FromStr for SimMethod {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "heuristic" => Ok(Self::Heuristic),
+            "linalg" => Ok(Self::LinAlg),
+            _ => Err(format!("unknown simulation method '{}'", s)),
+        }
+    }
+}
+
 /// Set of input shares required to simulate a set of probes.
 pub fn sim_set<T: IntoIterator<Item = u32>>(circ: &SlSharedCircuit, probes: T) -> Vec<u32> {
+    sim_set_with(circ, probes, SimMethod::Heuristic)
+}
+
+/// Set of input shares required to simulate a set of probes, using the given method.
+pub fn sim_set_with<T: IntoIterator<Item = u32>>(
+    circ: &SlSharedCircuit,
+    probes: T,
+    method: SimMethod,
+) -> Vec<u32> {
     let probes = probes
         .into_iter()
         .map(|x| x as usize)
         .collect::<Vec<usize>>();
     let (mut c, _) = CompGraphWork::from_circ_probes(circ, probes.iter().copied());
     c.simplify();
-    let inputs = c.inputs();
-    return inputs;
+    match method {
+        SimMethod::Heuristic => c.inputs(),
+        SimMethod::LinAlg => c.inputs_linalg(),
+    }
 }
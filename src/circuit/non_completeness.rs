@@ -0,0 +1,306 @@
+// STRAPS - Statistical Testing of RAndom Probing Security
+// Copyright (C) 2021 UCLouvain
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Non-completeness of threshold implementations.
+//!
+//! The component functions are the gates (other than registers), grouped by register stage: the
+//! maximum number of registers on a path from the inputs. A gate depends on the variables that
+//! appear in its ANF, where registers are variables: the component functions of stage k > 0
+//! depend on the register outputs, which are the shares of the sharings of stage k (and not on
+//! the input shares from which they are computed). A register of an input share (possibly
+//! through other registers) stands for that input share.
+
+use super::poly::BitSet;
+use super::sl_sc::{build_anfs, SlSharedCircuit, VarSrc};
+use super::var_set::VarIdx;
+use itertools::Itertools;
+use std::collections::BTreeMap;
+
+/// Component functions of the same register stage that jointly depend on all the shares of a
+/// sharing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NonCompletenessViolation {
+    pub stage: usize,
+    pub wires: Vec<String>,
+    /// Input port `p` is sharing `p`, the register sharings come next.
+    pub sharing: usize,
+}
+
+impl SlSharedCircuit {
+    /// Register stage of each variable.
+    pub fn register_stages(&self) -> Vec<usize> {
+        let mut res: Vec<usize> = Vec::with_capacity(self.vars.len());
+        for var in self.vars.iter() {
+            let stage = var.src.operands().iter().map(|op| res[*op]).max();
+            res.push(match var.src {
+                VarSrc::Reg(_) => stage.unwrap() + 1,
+                _ => stage.unwrap_or(0),
+            });
+        }
+        res
+    }
+
+    /// Check `order`-th order non-completeness: every set of `order` component functions of the
+    /// same register stage is independent of at least one share of each sharing. The sharings
+    /// are the input sharings, then the `register_sharings` (lists of registers, one per share).
+    /// These are required if some registers are not registers of input shares. Returns the
+    /// minimal violating sets.
+    pub fn check_non_completeness(
+        &self,
+        order: usize,
+        register_sharings: Option<&[Vec<VarIdx>]>,
+    ) -> Result<Vec<NonCompletenessViolation>, String> {
+        let stages = self.register_stages();
+        // Input share of each register of an input share, the variable itself otherwise.
+        let mut origin: Vec<VarIdx> = Vec::with_capacity(self.vars.len());
+        for (i, var) in self.vars.iter().enumerate() {
+            origin.push(match var.src {
+                VarSrc::Reg([op]) if matches!(self.vars[origin[op]].src, VarSrc::Input(..)) => {
+                    origin[op]
+                }
+                _ => i,
+            });
+        }
+        let mut sharings = self.input_sharings()?;
+        match register_sharings {
+            Some(register_sharings) => {
+                for sharing in register_sharings.iter() {
+                    if let Some(v) = sharing.iter().find(|v| {
+                        !matches!(self.vars.get(**v).map(|v| &v.src), Some(VarSrc::Reg(_)))
+                    }) {
+                        return Err(format!("variable {} is not a register", v));
+                    }
+                }
+                sharings.extend(
+                    register_sharings
+                        .iter()
+                        .map(|sharing| sharing.iter().map(|v| origin[*v]).collect()),
+                );
+            }
+            None => {
+                if let Some(reg) = self
+                    .vars
+                    .iter()
+                    .enumerate()
+                    .find(|(i, var)| matches!(var.src, VarSrc::Reg(_)) && origin[*i] == *i)
+                {
+                    return Err(format!(
+                        "register {} is not a register of an input share, the register sharings \
+                         must be given",
+                        reg.1.name
+                    ));
+                }
+            }
+        }
+        let deps = build_anfs(&self.vars, true)
+            .iter()
+            .map(|anf| {
+                anf.variables::<VarIdx>()
+                    .into_iter()
+                    .map(|v| origin[v])
+                    .collect::<BitSet>()
+            })
+            .collect::<Vec<_>>();
+        let full_sharing = |deps: &BitSet| {
+            sharings
+                .iter()
+                .position(|shares| shares.iter().all(|share| deps.contains(*share)))
+        };
+        let mut components: BTreeMap<usize, Vec<VarIdx>> = BTreeMap::new();
+        for (i, var) in self.vars.iter().enumerate() {
+            if !matches!(
+                var.src,
+                VarSrc::Input(..)
+                    | VarSrc::Random
+                    | VarSrc::Reg(_)
+                    | VarSrc::Const(_)
+                    | VarSrc::Public(_)
+            ) {
+                components.entry(stages[i]).or_default().push(i);
+            }
+        }
+        let mut res = Vec::new();
+        let mut violating: Vec<Vec<VarIdx>> = Vec::new();
+        for (stage, wires) in components {
+            for size in 1..=order {
+                for set in wires.iter().copied().combinations(size) {
+                    if violating.iter().any(|v| v.iter().all(|w| set.contains(w))) {
+                        continue;
+                    }
+                    let mut set_deps = BitSet::default();
+                    for w in set.iter() {
+                        set_deps.union_with(&deps[*w]);
+                    }
+                    if let Some(sharing) = full_sharing(&set_deps) {
+                        res.push(NonCompletenessViolation {
+                            stage,
+                            wires: set.iter().map(|w| self.vars[*w].name.clone()).collect(),
+                            sharing,
+                        });
+                        violating.push(set);
+                    }
+                }
+            }
+        }
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::circuit::{build_isw, new_sl_sc, NonCompletenessViolation, Var, VarSrc};
+
+    /// Adds the 3-share threshold implementation of the AND of sharings `x` and `y` to `vars`,
+    /// returns the output shares.
+    fn add_ti_and(vars: &mut Vec<Var>, x: [usize; 3], y: [usize; 3], name: &str) -> [usize; 3] {
+        let mut res = [0; 3];
+        for (share, out) in res.iter_mut().enumerate() {
+            // z_i = x_j y_j + x_j y_k + x_k y_j, with j = i+1, k = i+2
+            let (j, k) = ((share + 1) % 3, (share + 2) % 3);
+            let mut terms = Vec::new();
+            for (a, b) in [(j, j), (j, k), (k, j)] {
+                vars.push(Var::new(
+                    VarSrc::Product(vec![x[a], y[b]]),
+                    None,
+                    &format!("{}p{}{}", name, a, b),
+                ));
+                terms.push(vars.len() - 1);
+            }
+            vars.push(Var::new(
+                VarSrc::Sum(vec![terms[0], terms[1]]),
+                None,
+                &format!("{}s{}", name, share),
+            ));
+            vars.push(Var::new(
+                VarSrc::Sum(vec![vars.len() - 1, terms[2]]),
+                None,
+                &format!("{}{}", name, share),
+            ));
+            *out = vars.len() - 1;
+        }
+        res
+    }
+
+    /// Input sharings of `names`, with 3 shares.
+    fn inputs(names: &[&str]) -> Vec<Var> {
+        let mut vars = Vec::new();
+        for (port, name) in names.iter().enumerate() {
+            for share in 0..3 {
+                vars.push(Var::new(
+                    VarSrc::Input(port, share),
+                    None,
+                    &format!("{}{}", name, share),
+                ));
+            }
+        }
+        vars
+    }
+
+    /// First-order threshold implementation of the AND with 3 shares.
+    fn ti_and() -> crate::circuit::SlSharedCircuit {
+        let mut vars = inputs(&["x", "y"]);
+        let z = add_ti_and(&mut vars, [0, 1, 2], [3, 4, 5], "z");
+        for (share, v) in z.iter().enumerate() {
+            vars[*v].output_port = Some((0, share));
+        }
+        new_sl_sc(vars, 3, 2, 1).unwrap()
+    }
+
+    /// Two-stage threshold implementation of x*y*w with 3 shares: the TI AND of x and y, then,
+    /// after a register stage, the TI AND of the result and w. Returns the circuit and the
+    /// register sharings.
+    fn ti_and3() -> (crate::circuit::SlSharedCircuit, Vec<Vec<usize>>) {
+        let mut vars = inputs(&["x", "y", "w"]);
+        let z = add_ti_and(&mut vars, [0, 1, 2], [3, 4, 5], "z");
+        let mut regs = vec![Vec::new(), Vec::new()];
+        for (i, (name, sharing)) in [("rz", z), ("rw", [6, 7, 8])].iter().enumerate() {
+            for (share, v) in sharing.iter().enumerate() {
+                vars.push(Var::new(
+                    VarSrc::Reg([*v]),
+                    None,
+                    &format!("{}{}", name, share),
+                ));
+                regs[i].push(vars.len() - 1);
+            }
+        }
+        let out = add_ti_and(
+            &mut vars,
+            [regs[0][0], regs[0][1], regs[0][2]],
+            [regs[1][0], regs[1][1], regs[1][2]],
+            "o",
+        );
+        for (share, v) in out.iter().enumerate() {
+            vars[*v].output_port = Some((0, share));
+        }
+        (new_sl_sc(vars, 3, 3, 1).unwrap(), regs)
+    }
+
+    #[test]
+    fn test_non_completeness() {
+        let circ = ti_and();
+        assert_eq!(circ.check_non_completeness(1, None).unwrap(), vec![]);
+        let violations = circ.check_non_completeness(2, None).unwrap();
+        assert!(!violations.is_empty());
+        assert!(violations
+            .iter()
+            .all(|v| v.wires.len() == 2 && v.stage == 0));
+        // ISW is not a threshold implementation: the second output share depends on y_0 and y_1.
+        let violations = build_isw(2).check_non_completeness(1, None).unwrap();
+        assert!(violations
+            .iter()
+            .any(|v| v.wires == vec!["c_1_1".to_owned()] && v.sharing == 1));
+    }
+
+    #[test]
+    fn test_non_completeness_two_stages() {
+        let (circ, regs) = ti_and3();
+        // rz are not registers of input shares.
+        assert!(circ.check_non_completeness(1, None).is_err());
+        assert_eq!(circ.check_non_completeness(1, Some(&regs)).unwrap(), vec![]);
+        // With declared sharings, pairs of stage 1 components depend on all the shares of rz
+        // (sharing 3) or rw (sharing 4).
+        let violations = circ.check_non_completeness(2, Some(&regs)).unwrap();
+        assert!(violations
+            .iter()
+            .any(|v| v.stage == 1 && (v.sharing == 3 || v.sharing == 4)));
+        assert!(circ.check_non_completeness(1, Some(&[vec![0]])).is_err());
+    }
+
+    #[test]
+    fn test_non_completeness_input_registers() {
+        // Registers r0..r5 of the shares of x and y, o0 = r0 + r1 + r2 depends on all the
+        // shares of x.
+        let mut vars = inputs(&["x", "y"]);
+        for i in 0..6 {
+            vars.push(Var::new(VarSrc::Reg([i]), None, &format!("r{}", i)));
+        }
+        vars.push(Var::new(VarSrc::Sum(vec![6, 7, 8]), Some((0, 0)), "o0"));
+        vars.push(Var::new(VarSrc::Sum(vec![9, 10]), Some((0, 1)), "o1"));
+        vars.push(Var::new(VarSrc::Sum(vec![10, 11]), Some((0, 2)), "o2"));
+        let circ = new_sl_sc(vars, 3, 2, 1).unwrap();
+        let expected = vec![NonCompletenessViolation {
+            stage: 1,
+            wires: vec!["o0".to_owned()],
+            sharing: 0,
+        }];
+        assert_eq!(circ.check_non_completeness(1, None).unwrap(), expected);
+        let regs = [vec![6, 7, 8], vec![9, 10, 11]];
+        assert_eq!(
+            circ.check_non_completeness(1, Some(&regs)).unwrap(),
+            expected
+        );
+    }
+}
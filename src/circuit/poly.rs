@@ -147,7 +147,6 @@ impl Monomial {
 }
 
 impl Polynomial {
-    #[cfg(test)]
     pub(crate) fn zero() -> Self {
         Self(Vec::new())
     }
@@ -199,6 +198,18 @@ impl Polynomial {
     pub(crate) fn terms(&self) -> impl Iterator<Item = &Monomial> + Clone {
         self.0.iter()
     }
+    /// Substitute each variable `v` by the variable `f(v)`, or by 0 if `f(v)` is None.
+    pub(crate) fn map_vars<T: PolyIdx>(&self, f: impl Fn(T) -> Option<T>) -> Self {
+        Self::from_mon_vec(
+            self.terms()
+                .filter_map(|mon| {
+                    mon.variables::<T>()
+                        .map(|v| f(v).map(Monomial::from_var))
+                        .try_fold(Monomial::one(), |acc, v| v.map(|v| acc * v))
+                })
+                .collect(),
+        )
+    }
     pub(crate) fn from_mon_vec(mut mons: Vec<Monomial>) -> Self {
         mons.sort_unstable_by(|x, y| x.cmp(y).reverse());
         return Polynomial(remove_pairs(mons.into_iter()).collect());
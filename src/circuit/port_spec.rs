@@ -0,0 +1,144 @@
+// STRAPS - Statistical Testing of RAndom Probing Security
+// Copyright (C) 2021 UCLouvain
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Share, port and random annotations of netlist signals.
+//!
+//! Netlist formats do not tell which signals are shares of which sharing, this is given by a
+//! sidecar file such as
+//! ```text
+//! # ISW multiplication, 2 shares
+//! shares 2
+//! input a0 a1
+//! input b0 b1
+//! random r0
+//! output c0 c1
+//! ```
+//! Each `input` (resp. `output`) line is an input (resp. output) sharing, in port order, listing
+//! the names of its shares.
+
+/// Mapping of netlist signal names to input shares, output shares and randoms.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PortSpec {
+    pub n_shares: usize,
+    /// `inputs[port][share]` is the name of the signal carrying that input share.
+    pub inputs: Vec<Vec<String>>,
+    /// `outputs[port][share]` is the name of the signal carrying that output share.
+    pub outputs: Vec<Vec<String>>,
+    pub randoms: Vec<String>,
+}
+
+impl PortSpec {
+    /// Parse a sidecar annotation file.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut res = Self::default();
+        let mut n_shares = None;
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap();
+            let tokens = line.split_whitespace().collect::<Vec<_>>();
+            let names = || {
+                tokens[1..]
+                    .iter()
+                    .map(|s| (*s).to_owned())
+                    .collect::<Vec<_>>()
+            };
+            match tokens.first() {
+                None => {}
+                Some(&"shares") => match tokens[1..] {
+                    [n] if n_shares.is_none() => {
+                        n_shares = Some(n.parse::<usize>().map_err(|_| {
+                            format!("line {}: invalid number of shares '{}'", i + 1, n)
+                        })?);
+                    }
+                    _ => return Err(format!("line {}: expected a single 'shares n'", i + 1)),
+                },
+                Some(&"input") => res.inputs.push(names()),
+                Some(&"output") => res.outputs.push(names()),
+                Some(&"random") => res.randoms.extend(names()),
+                Some(kw) => return Err(format!("line {}: unknown keyword '{}'", i + 1, kw)),
+            }
+        }
+        match n_shares {
+            Some(n) => {
+                res.n_shares = n;
+                res.check()?;
+                Ok(res)
+            }
+            None => Self::new(res.inputs, res.outputs, res.randoms),
+        }
+    }
+
+    /// Spec whose number of shares is the size of the sharings.
+    pub fn new(
+        inputs: Vec<Vec<String>>,
+        outputs: Vec<Vec<String>>,
+        randoms: Vec<String>,
+    ) -> Result<Self, String> {
+        let n_shares = inputs
+            .iter()
+            .chain(outputs.iter())
+            .map(|sharing| sharing.len())
+            .next()
+            .ok_or("no input or output sharing")?;
+        let res = Self {
+            n_shares,
+            inputs,
+            outputs,
+            randoms,
+        };
+        res.check()?;
+        Ok(res)
+    }
+
+    /// Check that all sharings have `n_shares` shares and that names are not repeated.
+    pub fn check(&self) -> Result<(), String> {
+        for sharing in self.inputs.iter().chain(self.outputs.iter()) {
+            if sharing.len() != self.n_shares {
+                return Err(format!(
+                    "sharing {:?} does not have {} shares",
+                    sharing, self.n_shares
+                ));
+            }
+        }
+        if !super::utils::is_unique(
+            self.inputs
+                .iter()
+                .flatten()
+                .chain(self.randoms.iter())
+                .chain(self.outputs.iter().flatten()),
+        ) {
+            return Err("a signal has several roles".to_owned());
+        }
+        Ok(())
+    }
+
+    /// Role of a primary input signal: `Some((port, share))` for input shares, `None` for
+    /// randoms, error if the signal has no input role.
+    pub(crate) fn input_role(&self, name: &str) -> Result<Option<(usize, usize)>, String> {
+        for (port, sharing) in self.inputs.iter().enumerate() {
+            if let Some(share) = sharing.iter().position(|s| s == name) {
+                return Ok(Some((port, share)));
+            }
+        }
+        if self.randoms.iter().any(|s| s == name) {
+            Ok(None)
+        } else {
+            Err(format!(
+                "input '{}' is neither an input share nor a random",
+                name
+            ))
+        }
+    }
+}
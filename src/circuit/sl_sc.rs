@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use super::import_sl_sc::new_sl_sc;
 use super::poly::Polynomial;
 use std::fmt;
 
@@ -25,17 +26,27 @@ static EMPTY_USIZE_ARRAY: [usize; 0] = [];
 pub enum VarSrc {
     Input(usize, usize),
     Random,
-    Sum([VarIdx; 2]),
-    Product([VarIdx; 2]),
+    /// XOR of at least two operands.
+    Sum(Vec<VarIdx>),
+    /// AND of at least two operands.
+    Product(Vec<VarIdx>),
     Not([VarIdx; 1]),
+    /// Register: same value as its operand, but stops the propagation of glitches.
+    Reg([VarIdx; 1]),
+    /// Constant value.
+    Const(bool),
+    /// Unmasked value known to the adversary (e.g. a round constant or a tweak), with its name.
+    Public(String),
 }
 
 impl VarSrc {
     pub fn operands(&self) -> &[VarIdx] {
         match self {
-            VarSrc::Sum(ops) | VarSrc::Product(ops) => ops.as_ref(),
-            VarSrc::Not(ops) => ops.as_ref(),
-            VarSrc::Input(_, _) | VarSrc::Random => EMPTY_USIZE_ARRAY.as_ref(),
+            VarSrc::Sum(ops) | VarSrc::Product(ops) => ops.as_slice(),
+            VarSrc::Not(ops) | VarSrc::Reg(ops) => ops.as_ref(),
+            VarSrc::Input(_, _) | VarSrc::Random | VarSrc::Const(_) | VarSrc::Public(_) => {
+                EMPTY_USIZE_ARRAY.as_ref()
+            }
         }
     }
 }
@@ -47,6 +58,17 @@ pub struct Var {
     pub name: String,
 }
 
+#[cfg(test)]
+impl Var {
+    pub(crate) fn new(src: VarSrc, output_port: Option<(usize, usize)>, name: &str) -> Self {
+        Self {
+            src,
+            output_port,
+            name: name.to_owned(),
+        }
+    }
+}
+
 #[derive(Clone, PartialEq, Eq)]
 pub struct SlSharedCircuit {
     pub vars: Vec<Var>,
@@ -54,6 +76,8 @@ pub struct SlSharedCircuit {
     pub n_input_ports: usize,
     pub n_output_ports: usize,
     pub(crate) anfs: Vec<Polynomial>,
+    /// Physical neighbours of each wire (e.g. from placement or routing), see `set_adjacency`.
+    adjacency: Vec<Vec<VarIdx>>,
 }
 
 impl fmt::Debug for SlSharedCircuit {
@@ -83,26 +107,285 @@ impl SlSharedCircuit {
         n_input_ports: usize,
         n_output_ports: usize,
     ) -> Self {
-        let anfs = build_anfs(&vars);
+        let anfs = build_anfs(&vars, false);
+        let adjacency = vec![Vec::new(); vars.len()];
         Self {
             vars,
             n_shares,
             n_input_ports,
             n_output_ports,
             anfs,
+            adjacency,
+        }
+    }
+
+    /// Set the physical neighbours of the wires, given as a list of (undirected) pairs of
+    /// adjacent variables. Replaces any previous adjacency.
+    pub fn set_adjacency(&mut self, edges: &[(VarIdx, VarIdx)]) -> Result<(), String> {
+        let mut adjacency = vec![Vec::new(); self.vars.len()];
+        for (a, b) in edges.iter().copied() {
+            if let Some(v) = [a, b].iter().find(|v| **v >= self.vars.len()) {
+                return Err(format!("variable {} does not exist", v));
+            }
+            if a == b {
+                return Err(format!("variable {} is adjacent to itself", a));
+            }
+            adjacency[a].push(b);
+            adjacency[b].push(a);
+        }
+        for neighbours in adjacency.iter_mut() {
+            neighbours.sort_unstable();
+            neighbours.dedup();
+        }
+        self.adjacency = adjacency;
+        Ok(())
+    }
+
+    /// Fingerprint of the circuit (variables, ports, number of shares and adjacency), as 16 hexadecimal
+    /// digits, used to identify the circuit of stored PDTs. It hashes an explicit encoding of the
+    /// circuit, hence is stable across versions.
+    pub fn fingerprint(&self) -> String {
+        let mut enc = super::utils::ByteEncoder::default();
+        enc.int(self.n_shares as u64)
+            .int(self.n_input_ports as u64)
+            .int(self.n_output_ports as u64)
+            .int(self.vars.len() as u64);
+        for var in self.vars.iter() {
+            match &var.src {
+                VarSrc::Input(port, share) => enc.int(0).int(*port as u64).int(*share as u64),
+                VarSrc::Random => enc.int(1),
+                VarSrc::Sum(ops) => enc.int(2).indices(ops),
+                VarSrc::Product(ops) => enc.int(3).indices(ops),
+                VarSrc::Not(ops) => enc.int(4).indices(ops),
+                VarSrc::Reg(ops) => enc.int(5).indices(ops),
+                VarSrc::Const(value) => enc.int(6).int(*value as u64),
+                VarSrc::Public(name) => enc.int(7).str(name),
+            };
+            enc.port(var.output_port).str(&var.name);
+        }
+        for neighbours in self.adjacency.iter() {
+            enc.indices(neighbours);
+        }
+        enc.fingerprint()
+    }
+
+    /// Physical neighbours of `var`, in increasing order.
+    pub fn neighbours(&self, var: VarIdx) -> &[VarIdx] {
+        &self.adjacency[var]
+    }
+
+    /// Stable signals (registers, inputs and randoms) on which `var` combinationally depends,
+    /// in increasing order. These are the wires revealed by a glitch-extended probe on `var`.
+    pub fn glitch_extended(&self, var: VarIdx) -> Vec<VarIdx> {
+        let mut seen = vec![false; var + 1];
+        let mut stack = vec![var];
+        let mut res = Vec::new();
+        while let Some(v) = stack.pop() {
+            if std::mem::replace(&mut seen[v], true) {
+                continue;
+            }
+            match self.vars[v].src {
+                VarSrc::Input(_, _)
+                | VarSrc::Random
+                | VarSrc::Reg(_)
+                | VarSrc::Const(_)
+                | VarSrc::Public(_) => res.push(v),
+                VarSrc::Sum(_) | VarSrc::Product(_) | VarSrc::Not(_) => {
+                    stack.extend_from_slice(self.vars[v].src.operands())
+                }
+            }
+        }
+        res.sort_unstable();
+        res
+    }
+
+    /// Variable overwritten by each variable, given an execution order made of (variable, storage
+    /// slot) pairs. Variables that are not in `order` are not stored in any slot.
+    pub fn transitions(&self, order: &[(VarIdx, usize)]) -> Result<Vec<Option<VarIdx>>, String> {
+        let mut in_order = vec![false; self.vars.len()];
+        for (var, _) in order {
+            match in_order.get_mut(*var) {
+                None => return Err(format!("variable {} does not exist", var)),
+                Some(true) => return Err(format!("variable {} is stored twice", var)),
+                Some(x) => *x = true,
+            }
+        }
+        let mut prev = vec![None; self.vars.len()];
+        let mut done = vec![false; self.vars.len()];
+        let mut slots = std::collections::HashMap::new();
+        for (var, slot) in order {
+            if let Some(op) = self.vars[*var]
+                .src
+                .operands()
+                .iter()
+                .find(|op| in_order[**op] && !done[**op])
+            {
+                return Err(format!(
+                    "variable {} is computed before its operand {}",
+                    var, op
+                ));
+            }
+            prev[*var] = slots.insert(*slot, *var);
+            done[*var] = true;
+        }
+        Ok(prev)
+    }
+
+    /// Variables of the input shares, indexed by port then share.
+    pub(crate) fn input_sharings(&self) -> Result<Vec<Vec<VarIdx>>, String> {
+        self.sharings("input", self.n_input_ports, |var| match var.src {
+            VarSrc::Input(port, share) => Some((port, share)),
+            _ => None,
+        })
+    }
+
+    /// Variables of the output shares, indexed by port then share.
+    pub(crate) fn output_sharings(&self) -> Result<Vec<Vec<VarIdx>>, String> {
+        self.sharings("output", self.n_output_ports, |var| var.output_port)
+    }
+
+    fn sharings(
+        &self,
+        kind: &str,
+        n_ports: usize,
+        port_share: impl Fn(&Var) -> Option<(usize, usize)>,
+    ) -> Result<Vec<Vec<VarIdx>>, String> {
+        let mut res = vec![vec![None; self.n_shares]; n_ports];
+        for (i, var) in self.vars.iter().enumerate() {
+            if let Some((port, share)) = port_share(var) {
+                res[port][share] = Some(i);
+            }
+        }
+        res.into_iter()
+            .enumerate()
+            .map(|(port, shares)| {
+                shares
+                    .into_iter()
+                    .enumerate()
+                    .map(|(share, var)| {
+                        var.ok_or_else(|| format!("missing share {} of {} {}", share, kind, port))
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Number of gates using each variable as operand.
+    pub fn var_use_counts(&self) -> Vec<u32> {
+        let mut res = vec![0; self.vars.len()];
+        for var in self.vars.iter() {
+            for op in var.src.operands() {
+                res[*op] += 1;
+            }
+        }
+        res
+    }
+
+    /// Equivalent circuit where each gate with more than two operands is replaced by a balanced
+    /// tree of 2-input gates, whose internal nodes are named `x.1`, `x.2`, ... for a gate `x`.
+    /// Also returns the new index of each variable.
+    pub fn binarize(&self) -> Result<(Self, Vec<VarIdx>), String> {
+        let mut vars: Vec<Var> = Vec::with_capacity(self.vars.len());
+        let mut map = Vec::with_capacity(self.vars.len());
+        for var in self.vars.iter() {
+            let src = match &var.src {
+                VarSrc::Sum(ops) | VarSrc::Product(ops) => {
+                    let gate = |ops| match var.src {
+                        VarSrc::Sum(_) => VarSrc::Sum(ops),
+                        _ => VarSrc::Product(ops),
+                    };
+                    let mut level = ops.iter().map(|op| map[*op]).collect::<Vec<VarIdx>>();
+                    let mut n_nodes = 0;
+                    while level.len() > 2 {
+                        let mut next = Vec::with_capacity(level.len().div_ceil(2));
+                        for pair in level.chunks(2) {
+                            if pair.len() == 2 {
+                                n_nodes += 1;
+                                vars.push(Var {
+                                    src: gate(pair.to_vec()),
+                                    output_port: None,
+                                    name: format!("{}.{}", var.name, n_nodes),
+                                });
+                                next.push(vars.len() - 1);
+                            } else {
+                                next.push(pair[0]);
+                            }
+                        }
+                        level = next;
+                    }
+                    gate(level)
+                }
+                VarSrc::Not([op]) => VarSrc::Not([map[*op]]),
+                VarSrc::Reg([op]) => VarSrc::Reg([map[*op]]),
+                src => src.clone(),
+            };
+            vars.push(Var {
+                src,
+                output_port: var.output_port,
+                name: var.name.clone(),
+            });
+            map.push(vars.len() - 1);
         }
+        let mut res = new_sl_sc(vars, self.n_shares, self.n_input_ports, self.n_output_ports)?;
+        // The internal nodes of the trees have no known neighbours.
+        for (var, neighbours) in self.adjacency.iter().enumerate() {
+            res.adjacency[map[var]] = neighbours.iter().map(|v| map[*v]).collect();
+        }
+        Ok((res, map))
     }
 }
 
-fn build_anfs(vars: &[Var]) -> Vec<Polynomial> {
+/// ANF of each variable, with registers as variables if `regs_as_vars` (otherwise, they are
+/// transparent).
+pub(super) fn build_anfs(vars: &[Var], regs_as_vars: bool) -> Vec<Polynomial> {
     let mut res: Vec<Polynomial> = Vec::with_capacity(vars.len());
     for (i, var) in vars.iter().enumerate() {
-        res.push(match var.src {
-            VarSrc::Input(_, _) | VarSrc::Random => Polynomial::from_var(i as u32),
+        res.push(match &var.src {
+            VarSrc::Input(_, _) | VarSrc::Random | VarSrc::Public(_) => {
+                Polynomial::from_var(i as u32)
+            }
+            VarSrc::Reg(_) if regs_as_vars => Polynomial::from_var(i as u32),
+            VarSrc::Const(false) => Polynomial::zero(),
+            VarSrc::Const(true) => Polynomial::one(),
             VarSrc::Not(it) => res[it[0]].clone().not(),
-            VarSrc::Sum(it) => res[it[0]].clone() + res[it[1]].clone(),
-            VarSrc::Product(it) => res[it[0]].clone() * res[it[1]].clone(),
+            VarSrc::Reg(it) => res[it[0]].clone(),
+            VarSrc::Sum(it) => it
+                .iter()
+                .fold(Polynomial::zero(), |acc, op| acc + &res[*op]),
+            VarSrc::Product(it) => it.iter().fold(Polynomial::one(), |acc, op| acc * &res[*op]),
         });
     }
     return res;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::{sim_set_with, SimMethod};
+
+    #[test]
+    fn test_binarize() {
+        // Refresh of a0 with a 4-input XOR: b0 = a0 + a1 + r0 + r1, b1 = r0 + r1.
+        let vars = vec![
+            Var::new(VarSrc::Input(0, 0), None, "a0"),
+            Var::new(VarSrc::Input(0, 1), None, "a1"),
+            Var::new(VarSrc::Random, None, "r0"),
+            Var::new(VarSrc::Random, None, "r1"),
+            Var::new(VarSrc::Sum(vec![0, 1, 2, 3]), Some((0, 0)), "b0"),
+            Var::new(VarSrc::Sum(vec![2, 3]), Some((0, 1)), "b1"),
+        ];
+        let circ = new_sl_sc(vars, 2, 1, 1).unwrap();
+        let (bin, map) = circ.binarize().unwrap();
+        assert_eq!(map, vec![0, 1, 2, 3, 6, 7]);
+        assert_eq!(bin.vars[4].name, "b0.1");
+        assert_eq!(bin.vars[6].src, VarSrc::Sum(vec![4, 5]));
+        assert_eq!(bin.vars[6].output_port, Some((0, 0)));
+        assert_eq!(bin.anfs[6], circ.anfs[4]);
+        // The atomic gate is masked, the first node of the tree is a0 + a1.
+        assert_eq!(sim_set_with(&circ, vec![4], SimMethod::Heuristic), vec![]);
+        assert_eq!(
+            sim_set_with(&bin, vec![4], SimMethod::Heuristic),
+            vec![0, 1]
+        );
+    }
+}
@@ -0,0 +1,172 @@
+// STRAPS - Statistical Testing of RAndom Probing Security
+// Copyright (C) 2021 UCLouvain
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Verification of threshold probing security properties (t-NI, t-SNI, t-PINI).
+//!
+//! All sets of internal probes and output shares of total size at most `t` are enumerated, by
+//! increasing size. Since simulation sets are over-approximated (see [`SimMethod`]), a reported
+//! counterexample may be a false positive.
+
+use super::ni::{sim_set_with, SimMethod};
+use super::sl_sc::{SlSharedCircuit, VarSrc};
+use super::var_set::VarIdx;
+use itertools::Itertools;
+
+/// Threshold probing security property.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProbingProperty {
+    /// Non-Interference: `t1` internal probes and `t2` output shares can be simulated with
+    /// `t1 + t2` shares of each input.
+    Ni,
+    /// Strong Non-Interference: `t1` internal probes and `t2` output shares can be simulated with
+    /// `t1` shares of each input.
+    Sni,
+    /// Probe-Isolating Non-Interference: `t1` internal probes and the output shares of indices `O`
+    /// (of all output sharings) can be simulated with the input shares of indices `I + O`, with
+    /// `|I| <= t1`.
+    Pini,
+}
+
+impl std::str::FromStr for ProbingProperty {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "NI" => Ok(Self::Ni),
+            "SNI" => Ok(Self::Sni),
+            "PINI" => Ok(Self::Pini),
+            _ => Err(format!("unknown probing property '{}'", s)),
+        }
+    }
+}
+
+/// Probes that violate a property, given by variable names.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Counterexample {
+    pub probes: Vec<String>,
+    pub outputs: Vec<String>,
+    /// Input shares required to simulate the probes and outputs.
+    pub inputs: Vec<String>,
+}
+
+/// First counterexample to the t-`property` of `circ`, if any.
+pub fn check_probing(
+    circ: &SlSharedCircuit,
+    property: ProbingProperty,
+    t: usize,
+    method: SimMethod,
+) -> Option<Counterexample> {
+    // Constants and public values do not leak.
+    let internal = (0..circ.vars.len())
+        .filter(|i| {
+            circ.vars[*i].output_port.is_none()
+                && !matches!(circ.vars[*i].src, VarSrc::Const(_) | VarSrc::Public(_))
+        })
+        .collect::<Vec<_>>();
+    let outputs = (0..circ.vars.len())
+        .filter_map(|i| circ.vars[i].output_port.map(|(_, share)| (share, i)))
+        .collect::<Vec<_>>();
+    for t_tot in 1..=t {
+        for t_out in 0..=t_tot {
+            // Output sets: sets of output shares, or of share indices for PINI.
+            let output_sets: Vec<(Vec<usize>, Vec<VarIdx>)> = match property {
+                ProbingProperty::Ni | ProbingProperty::Sni => outputs
+                    .iter()
+                    .map(|(_, i)| *i)
+                    .combinations(t_out)
+                    .map(|vars| (Vec::new(), vars))
+                    .collect(),
+                ProbingProperty::Pini => (0..circ.n_shares)
+                    .combinations(t_out)
+                    .map(|shares| {
+                        let vars = outputs
+                            .iter()
+                            .filter(|(share, _)| shares.contains(share))
+                            .map(|(_, i)| *i)
+                            .collect();
+                        (shares, vars)
+                    })
+                    .collect(),
+            };
+            for probes in internal.iter().copied().combinations(t_tot - t_out) {
+                for (out_shares, out_vars) in output_sets.iter() {
+                    let observed = probes.iter().chain(out_vars.iter()).map(|x| *x as u32);
+                    let inputs = sim_set_with(circ, observed, method);
+                    let input_shares = inputs.iter().map(|i| match circ.vars[*i as usize].src {
+                        VarSrc::Input(port, share) => (port, share),
+                        _ => unreachable!(),
+                    });
+                    let secure = match property {
+                        ProbingProperty::Ni | ProbingProperty::Sni => {
+                            let bound = if property == ProbingProperty::Ni {
+                                t_tot
+                            } else {
+                                t_tot - t_out
+                            };
+                            input_shares
+                                .map(|(port, _)| port)
+                                .counts()
+                                .values()
+                                .all(|n| *n <= bound)
+                        }
+                        ProbingProperty::Pini => {
+                            input_shares
+                                .map(|(_, share)| share)
+                                .filter(|share| !out_shares.contains(share))
+                                .unique()
+                                .count()
+                                <= t_tot - t_out
+                        }
+                    };
+                    if !secure {
+                        let names = |vars: &mut dyn Iterator<Item = usize>| {
+                            vars.map(|i| circ.vars[i].name.clone()).collect()
+                        };
+                        return Some(Counterexample {
+                            probes: names(&mut probes.iter().copied()),
+                            outputs: names(&mut out_vars.iter().copied()),
+                            inputs: names(&mut inputs.iter().map(|i| *i as usize)),
+                        });
+                    }
+                }
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::build_isw;
+
+    #[test]
+    fn test_isw() {
+        let check = |n_shares, property, t| {
+            check_probing(&build_isw(n_shares), property, t, SimMethod::Heuristic)
+        };
+        assert_eq!(check(2, ProbingProperty::Ni, 1), None);
+        assert_eq!(check(2, ProbingProperty::Sni, 1), None);
+        assert_eq!(check(3, ProbingProperty::Sni, 2), None);
+        assert_eq!(
+            check(2, ProbingProperty::Pini, 1),
+            Some(Counterexample {
+                probes: vec!["p_0_1".to_owned()],
+                outputs: vec![],
+                inputs: vec!["x_0".to_owned(), "y_1".to_owned()],
+            })
+        );
+    }
+}
@@ -0,0 +1,258 @@
+// STRAPS - Statistical Testing of RAndom Probing Security
+// Copyright (C) 2021 UCLouvain
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Uniformity of output sharings.
+//!
+//! The input sharings are uniform sharings of fixed unmasked inputs, and public values are fixed.
+//! An output sharing of `n`
+//! shares is uniform iff its first `n-1` shares are uniform, that is iff the sum of every
+//! non-empty subset of them is balanced. This is checked exhaustively: for each value of the
+//! unmasked inputs, the distribution of the shares is computed by enumerating all the values of
+//! the free input shares and of the randoms, and the bias of every sum is obtained by a
+//! Walsh-Hadamard transform.
+//!
+//! This criterion assumes that the gadget is functionally correct (see `check_function`): the last
+//! share of each output sharing is then determined by the other ones and the unmasked output.
+
+use super::brute_sim::{cone, eval_bitsliced, LANE_PATTERNS};
+use super::sl_sc::{SlSharedCircuit, VarSrc};
+use super::var_set::VarIdx;
+
+/// Maximum number of enumerated bits (unmasked inputs, public values, free input shares and
+/// randoms).
+const MAX_ENUM_BITS: usize = 30;
+/// Maximum number of output shares whose joint distribution is computed.
+const MAX_TRACKED: usize = 20;
+
+/// Output shares whose sum is not uniform.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UniformityFailure {
+    /// Names of the output shares.
+    pub shares: Vec<String>,
+    /// Value of the unmasked inputs (bit `p` is input `p`) and of the public values (next bits, in
+    /// the order of the variables) for which the sum is biased.
+    pub secret: usize,
+    /// Probability that the sum is 1.
+    pub p_one: f64,
+}
+
+impl SlSharedCircuit {
+    /// Check that each output sharing is uniform given the unmasked inputs, and if `joint`,
+    /// that the output sharings are jointly uniform. Returns the failures: at most one per output
+    /// sharing, and a joint one only if all output sharings are uniform.
+    ///
+    /// This is exhaustive, hence only usable on small gadgets.
+    pub fn check_uniformity(&self, joint: bool) -> Result<Vec<UniformityFailure>, String> {
+        let n_shares = self.n_shares;
+        let input_shares = self.input_sharings()?;
+        // The last share of each output sharing is not tracked.
+        let tracked = self
+            .output_sharings()?
+            .into_iter()
+            .flat_map(|shares| shares[..n_shares - 1].to_vec())
+            .collect::<Vec<_>>();
+        let n_tracked_port = n_shares - 1;
+        let cone = cone(self, &tracked);
+        let mut in_cone = vec![false; self.vars.len()];
+        for i in cone.iter() {
+            in_cone[*i] = true;
+        }
+        // Input sharings whose first share is set by the unmasked input. When a share of a
+        // sharing is not in the cone, the other ones are independent and uniform, hence free.
+        let input_shares = input_shares
+            .into_iter()
+            .map(|shares| {
+                if shares.iter().all(|s| in_cone[*s]) {
+                    shares
+                } else {
+                    vec![]
+                }
+            })
+            .collect::<Vec<_>>();
+        // Free variables: randoms and input shares in the cone, except the set ones.
+        let free = cone
+            .iter()
+            .copied()
+            .filter(|i| match self.vars[*i].src {
+                VarSrc::Random => true,
+                VarSrc::Input(port, _) => input_shares[port].first() != Some(i),
+                _ => false,
+            })
+            .collect::<Vec<_>>();
+        let publics = cone
+            .iter()
+            .copied()
+            .filter(|i| matches!(self.vars[*i].src, VarSrc::Public(_)))
+            .collect::<Vec<_>>();
+        let n_secret_bits = self.n_input_ports + publics.len();
+        if tracked.len() > MAX_TRACKED || n_secret_bits + free.len() > MAX_ENUM_BITS {
+            return Err(format!(
+                "too many output shares ({}) or enumerated variables ({}) for uniformity check",
+                tracked.len(),
+                n_secret_bits + free.len()
+            ));
+        }
+        let total = 1i64 << free.len();
+        let mut failures: Vec<Option<UniformityFailure>> = vec![None; self.n_output_ports];
+        let mut joint_failure = None;
+        for secret in 0..(1usize << n_secret_bits) {
+            let spectrum =
+                self.shares_spectrum(&cone, &input_shares, &publics, &free, &tracked, secret);
+            let failure = |mask: usize| UniformityFailure {
+                shares: (0..tracked.len())
+                    .filter(|i| mask & (1 << i) != 0)
+                    .map(|i| self.vars[tracked[i]].name.clone())
+                    .collect(),
+                secret,
+                p_one: (total - spectrum[mask]) as f64 / (2 * total) as f64,
+            };
+            // Biased sum with the fewest shares among the masks accepted by `filter`.
+            let biased = |filter: &dyn Fn(usize) -> bool| {
+                (1..spectrum.len())
+                    .filter(|mask| spectrum[*mask] != 0 && filter(*mask))
+                    .min_by_key(|mask| mask.count_ones())
+            };
+            for (port, port_failure) in failures.iter_mut().enumerate() {
+                if port_failure.is_none() {
+                    let port_mask = ((1 << n_tracked_port) - 1) << (port * n_tracked_port);
+                    *port_failure = biased(&|mask| mask & !port_mask == 0).map(failure);
+                }
+            }
+            if joint && joint_failure.is_none() {
+                joint_failure = biased(&|_| true).map(failure);
+            }
+        }
+        let mut res = failures.into_iter().flatten().collect::<Vec<_>>();
+        if res.is_empty() {
+            res.extend(joint_failure);
+        }
+        Ok(res)
+    }
+
+    /// Walsh spectrum of the distribution of the `tracked` shares, for the unmasked inputs and
+    /// public values `secret`: `res[mask]` is the number of zeros minus the number of ones of the
+    /// sum of the shares in `mask`.
+    fn shares_spectrum(
+        &self,
+        cone: &[VarIdx],
+        input_shares: &[Vec<VarIdx>],
+        publics: &[VarIdx],
+        free: &[VarIdx],
+        tracked: &[VarIdx],
+        secret: usize,
+    ) -> Vec<i64> {
+        let mut words = vec![0u64; self.vars.len()];
+        let n_lanes = 1 << free.len().min(6);
+        let mut res = vec![0i64; 1 << tracked.len()];
+        for block in 0..(1u64 << free.len().saturating_sub(6)) {
+            for (j, var) in free.iter().enumerate() {
+                words[*var] = match LANE_PATTERNS.get(j) {
+                    Some(pattern) => *pattern,
+                    None => 0u64.wrapping_sub((block >> (j - 6)) & 1),
+                };
+            }
+            for (port, shares) in input_shares.iter().enumerate() {
+                if shares.is_empty() {
+                    continue;
+                }
+                let value = 0u64.wrapping_sub(((secret >> port) & 1) as u64);
+                words[shares[0]] = shares[1..].iter().fold(value, |acc, s| acc ^ words[*s]);
+            }
+            for (i, var) in publics.iter().enumerate() {
+                words[*var] = 0u64.wrapping_sub(((secret >> (input_shares.len() + i)) & 1) as u64);
+            }
+            eval_bitsliced(self, cone, &mut words);
+            for lane in 0..n_lanes {
+                let value = tracked
+                    .iter()
+                    .enumerate()
+                    .fold(0, |acc, (k, v)| acc | (((words[*v] >> lane) & 1) << k));
+                res[value as usize] += 1;
+            }
+        }
+        // Fast Walsh-Hadamard transform
+        let mut h = 1;
+        while h < res.len() {
+            for i in (0..res.len()).step_by(2 * h) {
+                for j in i..(i + h) {
+                    let (a, b) = (res[j], res[j + h]);
+                    res[j] = a + b;
+                    res[j + h] = a - b;
+                }
+            }
+            h *= 2;
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::circuit::{build_isw, new_sl_sc, Var, VarSrc};
+
+    #[test]
+    fn test_uniformity() {
+        assert_eq!(build_isw(3).check_uniformity(true), Ok(vec![]));
+        // Refresh a0 + r, a1 + r, copied (through registers) to a second output sharing.
+        let vars = vec![
+            Var::new(VarSrc::Input(0, 0), None, "a0"),
+            Var::new(VarSrc::Input(0, 1), None, "a1"),
+            Var::new(VarSrc::Random, None, "r"),
+            Var::new(VarSrc::Sum(vec![0, 2]), Some((0, 0)), "b0"),
+            Var::new(VarSrc::Sum(vec![1, 2]), Some((0, 1)), "b1"),
+            Var::new(VarSrc::Reg([3]), Some((1, 0)), "c0"),
+            Var::new(VarSrc::Reg([4]), Some((1, 1)), "c1"),
+        ];
+        let circ = new_sl_sc(vars.clone(), 2, 1, 2).unwrap();
+        assert_eq!(circ.check_uniformity(false), Ok(vec![]));
+        let failures = circ.check_uniformity(true).unwrap();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].shares, vec!["b0".to_owned(), "c0".to_owned()]);
+        assert_eq!(failures[0].p_one, 0.0);
+        // Without the random, b0 = a0*a1 is biased when a = 1.
+        let vars = vec![
+            Var::new(VarSrc::Input(0, 0), None, "a0"),
+            Var::new(VarSrc::Input(0, 1), None, "a1"),
+            Var::new(VarSrc::Product(vec![0, 1]), Some((0, 0)), "b0"),
+            Var::new(VarSrc::Sum(vec![0, 1]), None, "t"),
+            Var::new(VarSrc::Sum(vec![2, 3]), Some((0, 1)), "b1"),
+        ];
+        let circ = new_sl_sc(vars, 2, 1, 1).unwrap();
+        let failures = circ.check_uniformity(false).unwrap();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].shares, vec!["b0".to_owned()]);
+        assert_eq!(failures[0].secret, 1);
+        assert_eq!(failures[0].p_one, 0.0);
+    }
+    #[test]
+    fn test_uniformity_cone() {
+        // Output shares are registers of the shares of a, the shares of b are not in the cone
+        // (and do not count in the enumeration budget).
+        let n_shares = 16;
+        let vars = (0..2)
+            .flat_map(|port| {
+                (0..n_shares).map(move |s| {
+                    Var::new(VarSrc::Input(port, s), None, &format!("x{}_{}", port, s))
+                })
+            })
+            .chain(
+                (0..n_shares).map(|s| Var::new(VarSrc::Reg([s]), Some((0, s)), &format!("y{}", s))),
+            )
+            .collect();
+        let circ = new_sl_sc(vars, n_shares, 2, 1).unwrap();
+        assert_eq!(circ.check_uniformity(false), Ok(vec![]));
+    }
+}
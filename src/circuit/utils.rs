@@ -78,3 +78,51 @@ where
     }
     return true;
 }
+
+/// 64-bit FNV-1a hash, which is stable across platforms and versions (unlike `std` hashers).
+pub(crate) fn fnv1a(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Explicit byte encoding of a structure, for hashing with `fnv1a`: integers are 8 bytes
+/// little-endian, strings and lists are prefixed by their length.
+#[derive(Debug, Default)]
+pub(crate) struct ByteEncoder(Vec<u8>);
+
+impl ByteEncoder {
+    pub(crate) fn int(&mut self, x: u64) -> &mut Self {
+        self.0.extend_from_slice(&x.to_le_bytes());
+        self
+    }
+    pub(crate) fn ints(&mut self, xs: &[u64]) -> &mut Self {
+        self.int(xs.len() as u64);
+        for x in xs {
+            self.int(*x);
+        }
+        self
+    }
+    pub(crate) fn indices(&mut self, xs: &[usize]) -> &mut Self {
+        self.int(xs.len() as u64);
+        for x in xs {
+            self.int(*x as u64);
+        }
+        self
+    }
+    pub(crate) fn str(&mut self, s: &str) -> &mut Self {
+        self.int(s.len() as u64);
+        self.0.extend_from_slice(s.as_bytes());
+        self
+    }
+    pub(crate) fn port(&mut self, port: Option<(usize, usize)>) -> &mut Self {
+        match port {
+            Some((port, share)) => self.int(1).int(port as u64).int(share as u64),
+            None => self.int(0),
+        }
+    }
+    /// `fnv1a` of the encoding, as 16 hexadecimal digits.
+    pub(crate) fn fingerprint(&self) -> String {
+        format!("{:016x}", fnv1a(&self.0))
+    }
+}
@@ -0,0 +1,557 @@
+// STRAPS - Statistical Testing of RAndom Probing Security
+// Copyright (C) 2021 UCLouvain
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Word-level circuits, whose wires carry elements of a ring instead of bits.
+//!
+//! Each input and output port is a sharing of a ring element, either Boolean (the shares are
+//! XORed) or arithmetic (the shares are added in the ring). Boolean sharings and bitwise gates
+//! are only available for rings of bit strings (modulo 2^k and GF(2^n), where both sharings are
+//! the same).
+
+use super::utils;
+use super::var_set::VarIdx;
+
+/// Values carried by the wires of a [`WordCircuit`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ring {
+    /// Integers modulo 2^k, with 1 <= k <= 64.
+    Mod2k(u32),
+    /// Prime field GF(p).
+    Prime(u64),
+    /// Binary field GF(2^n) = GF(2)[x]/poly, with 1 <= n <= 63. `poly` is irreducible, of degree
+    /// n (bit i is the coefficient of x^i).
+    Gf2n { n: u32, poly: u64 },
+}
+
+impl Ring {
+    /// Number of elements, if it fits in a `u64`.
+    pub fn size(&self) -> Option<u64> {
+        match self {
+            Ring::Mod2k(k) => 1u64.checked_shl(*k),
+            Ring::Prime(p) => Some(*p),
+            Ring::Gf2n { n, .. } => Some(1 << n),
+        }
+    }
+
+    pub fn contains(&self, x: u64) -> bool {
+        match self {
+            Ring::Mod2k(k) => x & !low_bits(*k) == 0,
+            Ring::Prime(p) => x < *p,
+            Ring::Gf2n { n, .. } => x >> n == 0,
+        }
+    }
+
+    pub fn add(&self, a: u64, b: u64) -> u64 {
+        match self {
+            Ring::Mod2k(k) => a.wrapping_add(b) & low_bits(*k),
+            Ring::Prime(p) => ((a as u128 + b as u128) % *p as u128) as u64,
+            Ring::Gf2n { .. } => a ^ b,
+        }
+    }
+
+    pub fn sub(&self, a: u64, b: u64) -> u64 {
+        match self {
+            Ring::Mod2k(k) => a.wrapping_sub(b) & low_bits(*k),
+            Ring::Prime(p) => ((a as u128 + *p as u128 - b as u128) % *p as u128) as u64,
+            Ring::Gf2n { .. } => a ^ b,
+        }
+    }
+
+    pub fn mul(&self, a: u64, b: u64) -> u64 {
+        match self {
+            Ring::Mod2k(k) => a.wrapping_mul(b) & low_bits(*k),
+            Ring::Prime(p) => mul_mod(a, b, *p),
+            Ring::Gf2n { n, poly } => gf2n_mul(a, b, *n, *poly),
+        }
+    }
+
+    /// Whether multiplication by `c` is a bijection of the ring.
+    pub fn is_unit(&self, c: u64) -> bool {
+        match self {
+            Ring::Mod2k(_) => c % 2 == 1,
+            Ring::Prime(_) | Ring::Gf2n { .. } => c != 0,
+        }
+    }
+
+    /// Number of bits of the elements, if they are bit strings (i.e., Boolean sharings and
+    /// bitwise gates make sense).
+    pub fn bits(&self) -> Option<u32> {
+        match self {
+            Ring::Mod2k(k) => Some(*k),
+            Ring::Prime(_) => None,
+            Ring::Gf2n { n, .. } => Some(*n),
+        }
+    }
+
+    pub fn is_bitwise(&self) -> bool {
+        self.bits().is_some()
+    }
+
+    /// Unmasked value of a sharing.
+    pub fn unmask(&self, sharing: Sharing, shares: &[u64]) -> u64 {
+        match sharing {
+            Sharing::Boolean => shares.iter().fold(0, |acc, x| acc ^ x),
+            Sharing::Arithmetic => shares.iter().fold(0, |acc, x| self.add(acc, *x)),
+        }
+    }
+}
+
+/// Mask of the `k` low bits, for 1 <= k <= 64.
+fn low_bits(k: u32) -> u64 {
+    u64::MAX >> (64 - k)
+}
+
+fn mul_mod(a: u64, b: u64, m: u64) -> u64 {
+    (a as u128 * b as u128 % m as u128) as u64
+}
+
+fn pow_mod(mut a: u64, mut e: u64, m: u64) -> u64 {
+    let mut res = 1 % m;
+    while e != 0 {
+        if e & 1 == 1 {
+            res = mul_mod(res, a, m);
+        }
+        a = mul_mod(a, a, m);
+        e >>= 1;
+    }
+    res
+}
+
+/// Multiplication in GF(2)[x]/poly, with poly of degree n.
+fn gf2n_mul(a: u64, b: u64, n: u32, poly: u64) -> u64 {
+    let mut res = 0;
+    for i in (0..n).rev() {
+        res <<= 1;
+        if res >> n != 0 {
+            res ^= poly;
+        }
+        if (b >> i) & 1 == 1 {
+            res ^= a;
+        }
+    }
+    res
+}
+
+/// Degree of a non-zero polynomial over GF(2).
+fn degree(a: u64) -> u32 {
+    63 - a.leading_zeros()
+}
+
+fn gf2_poly_gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        while a != 0 && degree(a) >= degree(b) {
+            a ^= b << (degree(a) - degree(b));
+        }
+        std::mem::swap(&mut a, &mut b);
+    }
+    a
+}
+
+/// Rabin's test: poly (of degree n) is irreducible iff x^(2^n) = x mod poly and
+/// gcd(x^(2^(n/q)) - x, poly) = 1 for all prime divisors q of n.
+fn is_irreducible(n: u32, poly: u64) -> bool {
+    if n == 1 {
+        return true;
+    }
+    // x^(2^k) mod poly
+    let frobenius = |k: u32| (0..k).fold(2, |acc, _| gf2n_mul(acc, acc, n, poly));
+    frobenius(n) == 2
+        && (2..=n)
+            .filter(|q| n % *q == 0 && is_prime(*q as u64))
+            .all(|q| gf2_poly_gcd(frobenius(n / q) ^ 2, poly) == 1)
+}
+
+/// Image of `x` by the GF(2)-linear map whose i-th row is `rows[i]`: bit i of the result is the
+/// parity of `rows[i] & x`.
+pub(crate) fn gf2_linear(rows: &[u64], x: u64) -> u64 {
+    rows.iter().enumerate().fold(0, |acc, (i, row)| {
+        acc | (((row & x).count_ones() as u64) & 1) << i
+    })
+}
+
+/// Whether the square GF(2) matrix given by its rows is invertible.
+pub(crate) fn gf2_invertible(rows: &[u64]) -> bool {
+    let mut rows = rows.to_vec();
+    for col in 0..rows.len() {
+        let Some(pivot) = (col..rows.len()).find(|i| (rows[*i] >> col) & 1 == 1) else {
+            return false;
+        };
+        rows.swap(col, pivot);
+        for i in 0..rows.len() {
+            if i != col && (rows[i] >> col) & 1 == 1 {
+                rows[i] ^= rows[col];
+            }
+        }
+    }
+    true
+}
+
+/// Deterministic Miller-Rabin test (these bases are sufficient for all 64-bit integers).
+fn is_prime(n: u64) -> bool {
+    const BASES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+    if n < 2 {
+        return false;
+    }
+    if let Some(b) = BASES.iter().find(|b| n % **b == 0) {
+        return n == *b;
+    }
+    let s = (n - 1).trailing_zeros();
+    let d = (n - 1) >> s;
+    BASES.iter().all(|a| {
+        let mut x = pow_mod(*a, d, n);
+        if x == 1 || x == n - 1 {
+            return true;
+        }
+        for _ in 1..s {
+            x = mul_mod(x, x, n);
+            if x == n - 1 {
+                return true;
+            }
+        }
+        false
+    })
+}
+
+impl std::str::FromStr for Ring {
+    type Err = String;
+    /// `mod(2^k)`, `gf(p)` or `gf(2^n,poly)`, where `poly` is the modulus polynomial given as an
+    /// integer (e.g. `0x11b`), which can be omitted for the AES field GF(2^8).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || format!("unknown ring '{}'", s);
+        let s = s.replace(' ', "");
+        if let Some(field) = s.strip_prefix("gf(2^").and_then(|s| s.strip_suffix(')')) {
+            let (n, poly) = match field.split_once(',') {
+                Some((n, poly)) => (n, Some(poly)),
+                None => (field, None),
+            };
+            let n = n.parse::<u32>().map_err(|_| err())?;
+            let poly = match poly {
+                Some(poly) => match poly.strip_prefix("0x") {
+                    Some(hex) => u64::from_str_radix(hex, 16),
+                    None => poly.parse::<u64>(),
+                }
+                .map_err(|_| err())?,
+                None if n == 8 => 0x11b,
+                None => return Err(format!("missing modulus polynomial for GF(2^{})", n)),
+            };
+            if !(1..=63).contains(&n) || poly >> n != 1 || !is_irreducible(n, poly) {
+                return Err(format!(
+                    "{:#x} is not an irreducible polynomial of degree {}",
+                    poly, n
+                ));
+            }
+            return Ok(Ring::Gf2n { n, poly });
+        }
+        if let Some(p) = s.strip_prefix("gf(").and_then(|s| s.strip_suffix(')')) {
+            let p = p.parse::<u64>().map_err(|_| err())?;
+            if !is_prime(p) {
+                return Err(format!("{} is not prime", p));
+            }
+            return Ok(Ring::Prime(p));
+        }
+        let k = s
+            .strip_prefix("mod(2^")
+            .and_then(|s| s.strip_suffix(')'))
+            .ok_or_else(err)?
+            .parse::<u32>()
+            .map_err(|_| err())?;
+        if !(1..=64).contains(&k) {
+            return Err(format!("modulus 2^{} is not supported", k));
+        }
+        Ok(Ring::Mod2k(k))
+    }
+}
+
+/// How the shares of a port are combined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sharing {
+    /// XOR of the shares.
+    Boolean,
+    /// Sum of the shares in the ring (additive sharing).
+    Arithmetic,
+}
+
+impl std::str::FromStr for Sharing {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "boolean" => Ok(Self::Boolean),
+            "arithmetic" => Ok(Self::Arithmetic),
+            _ => Err(format!("unknown sharing '{}'", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WordSrc {
+    Input(usize, usize),
+    /// Uniform element of the ring.
+    Random,
+    Const(u64),
+    AddMod([VarIdx; 2]),
+    /// First operand minus the second one.
+    SubMod([VarIdx; 2]),
+    /// Multiplication in the ring (the field multiplication in GF(p) and GF(2^n)).
+    MulMod([VarIdx; 2]),
+    /// Multiplication by a constant.
+    MulConst([VarIdx; 1], u64),
+    /// Square of the operand (a linear map in GF(2^n)).
+    FieldSquare([VarIdx; 1]),
+    /// GF(2)-linear map of the bits of the operand, given by the rows of its matrix (see
+    /// `Linear` in [`WordCircuit::new`]).
+    Linear([VarIdx; 1], Vec<u64>),
+    /// GF(2)-linear map followed by the XOR of a constant.
+    Affine([VarIdx; 1], Vec<u64>, u64),
+    /// Bitwise XOR.
+    Xor([VarIdx; 2]),
+    /// Bitwise AND.
+    And([VarIdx; 2]),
+}
+
+impl WordSrc {
+    pub fn operands(&self) -> &[VarIdx] {
+        match self {
+            WordSrc::AddMod(ops)
+            | WordSrc::SubMod(ops)
+            | WordSrc::MulMod(ops)
+            | WordSrc::Xor(ops)
+            | WordSrc::And(ops) => ops.as_ref(),
+            WordSrc::MulConst(ops, _)
+            | WordSrc::FieldSquare(ops)
+            | WordSrc::Linear(ops, _)
+            | WordSrc::Affine(ops, _, _) => ops.as_ref(),
+            WordSrc::Input(_, _) | WordSrc::Random | WordSrc::Const(_) => &[],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WordVar {
+    pub src: WordSrc,
+    pub output_port: Option<(usize, usize)>,
+    pub name: String,
+}
+
+#[cfg(test)]
+impl WordVar {
+    pub(crate) fn new(src: WordSrc, output_port: Option<(usize, usize)>, name: &str) -> Self {
+        Self {
+            src,
+            output_port,
+            name: name.to_owned(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WordCircuit {
+    pub ring: Ring,
+    pub vars: Vec<WordVar>,
+    pub n_shares: usize,
+    /// Kind of each input (resp. output) port.
+    pub input_sharings: Vec<Sharing>,
+    pub output_sharings: Vec<Sharing>,
+}
+
+impl WordCircuit {
+    /// The matrix of a `Linear` or `Affine` gate has one row per bit of the ring elements, bit j
+    /// of row i is the coefficient of input bit j in output bit i.
+    pub fn new(
+        ring: Ring,
+        vars: Vec<WordVar>,
+        n_shares: usize,
+        input_sharings: Vec<Sharing>,
+        output_sharings: Vec<Sharing>,
+    ) -> Result<Self, String> {
+        if !utils::is_unique(vars.iter().map(|v| &v.name)) {
+            return Err("Non unique names".to_owned());
+        }
+        if !ring.is_bitwise()
+            && input_sharings
+                .iter()
+                .chain(output_sharings.iter())
+                .any(|s| *s == Sharing::Boolean)
+        {
+            return Err(format!("Boolean sharings are not supported in {:?}", ring));
+        }
+        let mut inputs = vec![vec![false; n_shares]; input_sharings.len()];
+        let mut outputs = vec![vec![false; n_shares]; output_sharings.len()];
+        let mark =
+            |ports: &mut Vec<Vec<bool>>, (port, share): (usize, usize), kind: &str| match ports
+                .get_mut(port)
+                .and_then(|p| p.get_mut(share))
+            {
+                Some(seen @ false) => {
+                    *seen = true;
+                    Ok(())
+                }
+                Some(true) => Err(format!("Duplicate {} share ({}, {})", kind, port, share)),
+                None => Err(format!("Invalid {} share ({}, {})", kind, port, share)),
+            };
+        for (i, var) in vars.iter().enumerate() {
+            if let Some(op) = var.src.operands().iter().find(|op| **op >= i) {
+                return Err(format!("Invalid op sorting, {} {}", i, op));
+            }
+            match &var.src {
+                WordSrc::Input(port, share) => mark(&mut inputs, (*port, *share), "input")?,
+                WordSrc::Const(c) | WordSrc::MulConst(_, c) | WordSrc::Affine(_, _, c)
+                    if !ring.contains(*c) =>
+                {
+                    return Err(format!("constant {} is not an element of {:?}", c, ring));
+                }
+                WordSrc::Linear(_, rows) | WordSrc::Affine(_, rows, _)
+                    if ring.bits() != Some(rows.len() as u32)
+                        || !rows.iter().all(|row| ring.contains(*row)) =>
+                {
+                    return Err(format!(
+                        "matrix of {} is not a square matrix of the bits of {:?}",
+                        var.name, ring
+                    ));
+                }
+                WordSrc::Xor(_) | WordSrc::And(_) if !ring.is_bitwise() => {
+                    return Err(format!(
+                        "bitwise gate {} is not supported in {:?}",
+                        var.name, ring
+                    ));
+                }
+                _ => {}
+            }
+            if let Some(port_share) = var.output_port {
+                mark(&mut outputs, port_share, "output")?;
+            }
+        }
+        for (ports, kind) in [(inputs, "input"), (outputs, "output")] {
+            for (port, shares) in ports.iter().enumerate() {
+                if let Some(share) = shares.iter().position(|seen| !seen) {
+                    return Err(format!("Missing {} share ({}, {})", kind, port, share));
+                }
+            }
+        }
+        Ok(Self {
+            ring,
+            vars,
+            n_shares,
+            input_sharings,
+            output_sharings,
+        })
+    }
+
+    /// Fingerprint of the circuit, see `SlSharedCircuit::fingerprint`.
+    pub fn fingerprint(&self) -> String {
+        let mut enc = utils::ByteEncoder::default();
+        match self.ring {
+            Ring::Mod2k(k) => enc.int(0).int(k as u64),
+            Ring::Prime(p) => enc.int(1).int(p),
+            Ring::Gf2n { n, poly } => enc.int(2).int(n as u64).int(poly),
+        };
+        enc.int(self.n_shares as u64);
+        for sharings in [&self.input_sharings, &self.output_sharings] {
+            enc.int(sharings.len() as u64);
+            for sharing in sharings.iter() {
+                enc.int(match sharing {
+                    Sharing::Boolean => 0,
+                    Sharing::Arithmetic => 1,
+                });
+            }
+        }
+        enc.int(self.vars.len() as u64);
+        for var in self.vars.iter() {
+            match &var.src {
+                WordSrc::Input(port, share) => enc.int(0).int(*port as u64).int(*share as u64),
+                WordSrc::Random => enc.int(1),
+                WordSrc::Const(c) => enc.int(2).int(*c),
+                WordSrc::AddMod(ops) => enc.int(3).indices(ops),
+                WordSrc::SubMod(ops) => enc.int(4).indices(ops),
+                WordSrc::MulMod(ops) => enc.int(5).indices(ops),
+                WordSrc::MulConst(ops, c) => enc.int(6).indices(ops).int(*c),
+                WordSrc::FieldSquare(ops) => enc.int(7).indices(ops),
+                WordSrc::Linear(ops, rows) => enc.int(8).indices(ops).ints(rows),
+                WordSrc::Affine(ops, rows, c) => enc.int(9).indices(ops).ints(rows).int(*c),
+                WordSrc::Xor(ops) => enc.int(10).indices(ops),
+                WordSrc::And(ops) => enc.int(11).indices(ops),
+            };
+            enc.port(var.output_port).str(&var.name);
+        }
+        enc.fingerprint()
+    }
+
+    pub fn n_input_ports(&self) -> usize {
+        self.input_sharings.len()
+    }
+
+    pub fn n_output_ports(&self) -> usize {
+        self.output_sharings.len()
+    }
+
+    /// Number of gates using each variable as operand.
+    pub fn var_use_counts(&self) -> Vec<u32> {
+        let mut res = vec![0; self.vars.len()];
+        for var in self.vars.iter() {
+            for op in var.src.operands() {
+                res[*op] += 1;
+            }
+        }
+        res
+    }
+
+    /// Value of `var`, given the values of its operands (and of itself if it is an input or a
+    /// random) in `values`.
+    pub(crate) fn gate_value(&self, var: VarIdx, values: &[u64]) -> u64 {
+        let ring = &self.ring;
+        match &self.vars[var].src {
+            WordSrc::Input(_, _) | WordSrc::Random => values[var],
+            WordSrc::Const(c) => *c,
+            WordSrc::AddMod([a, b]) => ring.add(values[*a], values[*b]),
+            WordSrc::SubMod([a, b]) => ring.sub(values[*a], values[*b]),
+            WordSrc::MulMod([a, b]) => ring.mul(values[*a], values[*b]),
+            WordSrc::MulConst([a], c) => ring.mul(values[*a], *c),
+            WordSrc::FieldSquare([a]) => ring.mul(values[*a], values[*a]),
+            WordSrc::Linear([a], rows) => gf2_linear(rows, values[*a]),
+            WordSrc::Affine([a], rows, c) => gf2_linear(rows, values[*a]) ^ c,
+            WordSrc::Xor([a, b]) => values[*a] ^ values[*b],
+            WordSrc::And([a, b]) => values[*a] & values[*b],
+        }
+    }
+
+    /// Output shares (port-major) for the given input shares (port-major) and randoms (in the
+    /// order of the variables).
+    pub fn eval(&self, input_shares: &[u64], randoms: &[u64]) -> Result<Vec<u64>, String> {
+        let mut values = vec![0; self.vars.len()];
+        let mut randoms = randoms.iter();
+        for i in 0..self.vars.len() {
+            values[i] = match self.vars[i].src {
+                WordSrc::Input(port, share) => *input_shares
+                    .get(port * self.n_shares + share)
+                    .ok_or("not enough input shares")?,
+                WordSrc::Random => *randoms.next().ok_or("not enough randoms")?,
+                _ => self.gate_value(i, &values),
+            };
+            if !self.ring.contains(values[i]) {
+                return Err(format!(
+                    "{} is not an element of {:?}",
+                    values[i], self.ring
+                ));
+            }
+        }
+        let mut res = vec![0; self.n_output_ports() * self.n_shares];
+        for (var, value) in self.vars.iter().zip(values) {
+            if let Some((port, share)) = var.output_port {
+                res[port * self.n_shares + share] = value;
+            }
+        }
+        Ok(res)
+    }
+}
@@ -0,0 +1,396 @@
+// STRAPS - Statistical Testing of RAndom Probing Security
+// Copyright (C) 2021 UCLouvain
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Simulatability in word-level circuits.
+//!
+//! The heuristic is the word-level counterpart of the graph simplification of bit-level
+//! circuits: a random that is used by a single gate, which is a bijection of that random for any
+//! value of its other operands, makes the gate uniform and independent of everything else. Such
+//! gates are additions, subtractions, XORs, multiplications by a unit constant (any non-zero
+//! constant in a field), squares in GF(2^n) and invertible linear or affine maps. The
+//! gate is then replaced by a fresh random, until no random can be removed. The exhaustive
+//! method is exact, but is only usable with small rings and circuits.
+
+use super::var_set::VarIdx;
+use super::word::{gf2_invertible, Ring, WordCircuit, WordSrc};
+use std::collections::HashMap;
+
+/// Maximum number of circuit evaluations for exhaustive simulation.
+const MAX_EXHAUSTIVE_EVALS: u64 = 1 << 24;
+
+/// Algorithm used to find the input shares required to simulate a set of probes in a
+/// [`WordCircuit`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WordSimMethod {
+    Heuristic,
+    Exhaustive,
+}
+
+impl std::str::FromStr for WordSimMethod {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "heuristic" => Ok(Self::Heuristic),
+            "exhaustive" => Ok(Self::Exhaustive),
+            _ => Err(format!("unknown simulation method '{}'", s)),
+        }
+    }
+}
+
+impl WordCircuit {
+    /// Input shares (variables) required to simulate the `probes` (variables), in increasing
+    /// order.
+    pub fn sim_set(&self, probes: &[VarIdx], method: WordSimMethod) -> Result<Vec<VarIdx>, String> {
+        if let Some(p) = probes.iter().find(|p| **p >= self.vars.len()) {
+            return Err(format!("variable {} does not exist", p));
+        }
+        match method {
+            WordSimMethod::Heuristic => Ok(self.sim_set_heuristic(probes)),
+            WordSimMethod::Exhaustive => self.sim_set_exhaustive(probes),
+        }
+    }
+
+    /// Whether `gate` is a bijection of its operand `op`, for any value of the other one.
+    fn randomized_by(&self, gate: VarIdx, op: VarIdx) -> bool {
+        let is_unit = |var: VarIdx| match self.vars[var].src {
+            WordSrc::Const(c) => self.ring.is_unit(c),
+            _ => false,
+        };
+        match &self.vars[gate].src {
+            WordSrc::AddMod(_) | WordSrc::SubMod(_) | WordSrc::Xor(_) => true,
+            WordSrc::MulConst(_, c) => self.ring.is_unit(*c),
+            WordSrc::MulMod([a, b]) => a != b && is_unit(if *a == op { *b } else { *a }),
+            WordSrc::FieldSquare(_) => matches!(self.ring, Ring::Gf2n { .. }),
+            WordSrc::Linear(_, rows) | WordSrc::Affine(_, rows, _) => gf2_invertible(rows),
+            WordSrc::And(_) => false,
+            WordSrc::Input(_, _) | WordSrc::Random | WordSrc::Const(_) => unreachable!(),
+        }
+    }
+
+    fn sim_set_heuristic(&self, probes: &[VarIdx]) -> Vec<VarIdx> {
+        let n_vars = self.vars.len();
+        let mut probed = vec![false; n_vars];
+        for p in probes {
+            probed[*p] = true;
+        }
+        // Graph restricted to the cone of the probes.
+        let mut in_graph = probed.clone();
+        let mut operands = vec![Vec::new(); n_vars];
+        let mut successors = vec![Vec::new(); n_vars];
+        for i in (0..n_vars).rev() {
+            if in_graph[i] {
+                operands[i] = self.vars[i].src.operands().to_vec();
+                for op in operands[i].iter() {
+                    in_graph[*op] = true;
+                    successors[*op].push(i);
+                }
+            }
+        }
+        let mut random = self
+            .vars
+            .iter()
+            .map(|var| var.src == WordSrc::Random)
+            .collect::<Vec<_>>();
+        let mut stack = (0..n_vars)
+            .filter(|i| in_graph[*i] && random[*i] && !probed[*i])
+            .collect::<Vec<_>>();
+        while let Some(r) = stack.pop() {
+            if !in_graph[r] || successors[r].len() != 1 || !self.randomized_by(successors[r][0], r)
+            {
+                continue;
+            }
+            // The gate becomes a fresh random: remove its incoming edges, and the variables that
+            // are not used anymore.
+            let gate = successors[r][0];
+            random[gate] = true;
+            if !probed[gate] {
+                stack.push(gate);
+            }
+            let mut removed_edges = std::mem::take(&mut operands[gate])
+                .into_iter()
+                .map(|op| (op, gate))
+                .collect::<Vec<_>>();
+            while let Some((op, succ)) = removed_edges.pop() {
+                let pos = successors[op].iter().position(|s| *s == succ).unwrap();
+                successors[op].swap_remove(pos);
+                if probed[op] {
+                    continue;
+                }
+                if successors[op].is_empty() {
+                    in_graph[op] = false;
+                    removed_edges.extend(
+                        std::mem::take(&mut operands[op])
+                            .into_iter()
+                            .map(|o| (o, op)),
+                    );
+                } else if random[op] {
+                    stack.push(op);
+                }
+            }
+        }
+        (0..n_vars)
+            .filter(|i| in_graph[*i] && matches!(self.vars[*i].src, WordSrc::Input(_, _)))
+            .collect()
+    }
+
+    /// Variables on which `vars` depend (including themselves), in increasing order.
+    fn cone(&self, vars: &[VarIdx]) -> Vec<VarIdx> {
+        let mut in_cone = vec![false; self.vars.len()];
+        for v in vars.iter() {
+            in_cone[*v] = true;
+        }
+        for i in (0..self.vars.len()).rev() {
+            if in_cone[i] {
+                for op in self.vars[i].src.operands() {
+                    in_cone[*op] = true;
+                }
+            }
+        }
+        (0..self.vars.len()).filter(|i| in_cone[*i]).collect()
+    }
+
+    /// Number of values of `n_inputs` input shares and of `n_randoms` randoms, if they can be
+    /// enumerated.
+    pub(crate) fn exhaustive_size(
+        &self,
+        n_inputs: usize,
+        n_randoms: usize,
+    ) -> Result<(u64, u64), String> {
+        let err = || {
+            format!(
+                "too many input shares ({}) or randoms ({}) for exhaustive simulation",
+                n_inputs, n_randoms
+            )
+        };
+        let size = self.ring.size().ok_or_else(err)?;
+        let pow = |n: usize| size.checked_pow(n as u32).ok_or_else(err);
+        let (n_x, n_r) = (pow(n_inputs)?, pow(n_randoms)?);
+        match n_x.checked_mul(n_r) {
+            Some(n) if n <= MAX_EXHAUSTIVE_EVALS => Ok((n_x, n_r)),
+            _ => Err(err()),
+        }
+    }
+
+    /// The distribution of the probes depends on an input share iff changing that share changes
+    /// it, for some value of the other shares.
+    fn sim_set_exhaustive(&self, probes: &[VarIdx]) -> Result<Vec<VarIdx>, String> {
+        let cone = self.cone(probes);
+        let of_kind = |kind: fn(&WordSrc) -> bool| {
+            cone.iter()
+                .copied()
+                .filter(|i| kind(&self.vars[*i].src))
+                .collect::<Vec<_>>()
+        };
+        let inputs = of_kind(|src| matches!(src, WordSrc::Input(_, _)));
+        let randoms = of_kind(|src| *src == WordSrc::Random);
+        let (n_x, n_r) = self.exhaustive_size(inputs.len(), randoms.len())?;
+        let size = self.ring.size().unwrap();
+        let mut values = vec![0; self.vars.len()];
+        let mut distribution = |x: u64| {
+            let mut hist: HashMap<Vec<u64>, u64> = HashMap::new();
+            for (i, var) in inputs.iter().enumerate() {
+                values[*var] = x / size.pow(i as u32) % size;
+            }
+            for r in 0..n_r {
+                for (i, var) in randoms.iter().enumerate() {
+                    values[*var] = r / size.pow(i as u32) % size;
+                }
+                for var in cone.iter() {
+                    values[*var] = self.gate_value(*var, &values);
+                }
+                *hist
+                    .entry(probes.iter().map(|p| values[*p]).collect())
+                    .or_insert(0) += 1;
+            }
+            let mut hist = hist.into_iter().collect::<Vec<_>>();
+            hist.sort_unstable();
+            hist
+        };
+        let distrs = (0..n_x).map(&mut distribution).collect::<Vec<_>>();
+        Ok(inputs
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| {
+                let weight = size.pow(*i as u32);
+                (0..n_x).any(|x| {
+                    distrs[x as usize] != distrs[(x - x / weight % size * weight) as usize]
+                })
+            })
+            .map(|(_, var)| *var)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::{build_word_isw, build_word_square, Ring, Sharing, WordVar};
+
+    /// Goubin's Boolean-to-arithmetic conversion of (x0, x1), with x = x0 ^ x1, into
+    /// (a, x1) with x = a + x1.
+    fn goubin_b2a(k: u32) -> WordCircuit {
+        let vars = vec![
+            WordVar::new(WordSrc::Input(0, 0), None, "x0"),
+            WordVar::new(WordSrc::Input(0, 1), Some((0, 1)), "x1"),
+            WordVar::new(WordSrc::Random, None, "g"),
+            WordVar::new(WordSrc::Xor([0, 2]), None, "t0"),
+            WordVar::new(WordSrc::SubMod([3, 2]), None, "t1"),
+            WordVar::new(WordSrc::Xor([4, 0]), None, "t2"),
+            WordVar::new(WordSrc::Xor([2, 1]), None, "g1"),
+            WordVar::new(WordSrc::Xor([0, 6]), None, "a0"),
+            WordVar::new(WordSrc::SubMod([7, 6]), None, "a1"),
+            WordVar::new(WordSrc::Xor([8, 5]), Some((0, 0)), "a"),
+        ];
+        WordCircuit::new(
+            Ring::Mod2k(k),
+            vars,
+            2,
+            vec![Sharing::Boolean],
+            vec![Sharing::Arithmetic],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_goubin_b2a() {
+        let circ = goubin_b2a(3);
+        for x0 in 0..8 {
+            for x1 in 0..8 {
+                for g in 0..8 {
+                    let out = circ.eval(&[x0, x1], &[g]).unwrap();
+                    assert_eq!(
+                        circ.ring.unmask(Sharing::Arithmetic, &out),
+                        circ.ring.unmask(Sharing::Boolean, &[x0, x1])
+                    );
+                }
+            }
+        }
+        // First-order secure, although the heuristic cannot prove it for t1 = (x0 ^ g) - g.
+        for probe in 0..circ.vars.len() - 1 {
+            let exact = circ.sim_set(&[probe], WordSimMethod::Exhaustive).unwrap();
+            let heuristic = circ.sim_set(&[probe], WordSimMethod::Heuristic).unwrap();
+            assert!(exact.len() <= 1, "probe {}: {:?}", probe, exact);
+            assert!(exact.iter().all(|x| heuristic.contains(x)));
+        }
+        assert_eq!(circ.sim_set(&[3], WordSimMethod::Heuristic), Ok(vec![]));
+        assert_eq!(circ.sim_set(&[4], WordSimMethod::Heuristic), Ok(vec![0]));
+        assert_eq!(
+            circ.sim_set(&[9, 1], WordSimMethod::Exhaustive),
+            Ok(vec![0, 1])
+        );
+    }
+
+    #[test]
+    fn test_prime_isw() {
+        let ring: Ring = "gf(5)".parse().unwrap();
+        assert_eq!(ring, Ring::Prime(5));
+        assert!("gf(6)".parse::<Ring>().is_err());
+        let circ = build_word_isw(ring, 2);
+        for x in 0..25 {
+            let (x0, x1, y0, y1) = (x % 5, x / 5, (x + 2) % 5, (3 * x) % 5);
+            for r in 0..5 {
+                let out = circ.eval(&[x0, x1, y0, y1], &[r]).unwrap();
+                assert_eq!(
+                    ring.unmask(Sharing::Arithmetic, &out),
+                    ring.mul(ring.add(x0, x1), ring.add(y0, y1))
+                );
+            }
+        }
+        for probe in 0..circ.vars.len() {
+            let exact = circ.sim_set(&[probe], WordSimMethod::Exhaustive).unwrap();
+            let heuristic = circ.sim_set(&[probe], WordSimMethod::Heuristic).unwrap();
+            assert!(exact.iter().all(|x| heuristic.contains(x)));
+            assert!(exact.len() <= 2, "probe {}: {:?}", probe, exact);
+        }
+        // Multiplication by a non-zero constant preserves the uniformity of a random.
+        let circ = WordCircuit::new(
+            ring,
+            vec![
+                WordVar::new(WordSrc::Input(0, 0), Some((0, 0)), "a0"),
+                WordVar::new(WordSrc::Input(0, 1), None, "a1"),
+                WordVar::new(WordSrc::Random, None, "r"),
+                WordVar::new(WordSrc::MulConst([2], 3), None, "s"),
+                WordVar::new(WordSrc::Const(0), None, "zero"),
+                WordVar::new(WordSrc::MulMod([2, 4]), None, "t"),
+                WordVar::new(WordSrc::AddMod([1, 3]), Some((0, 1)), "b1"),
+                WordVar::new(WordSrc::AddMod([1, 5]), None, "c1"),
+            ],
+            2,
+            vec![Sharing::Arithmetic],
+            vec![Sharing::Arithmetic],
+        )
+        .unwrap();
+        assert_eq!(circ.sim_set(&[6], WordSimMethod::Heuristic), Ok(vec![]));
+        assert_eq!(circ.sim_set(&[7], WordSimMethod::Heuristic), Ok(vec![1]));
+        assert_eq!(circ.sim_set(&[7], WordSimMethod::Exhaustive), Ok(vec![1]));
+    }
+
+    #[test]
+    fn test_gf2n() {
+        let aes: Ring = "gf(2^8)".parse().unwrap();
+        assert_eq!(aes.mul(0x53, 0xca), 1);
+        assert_eq!(aes.mul(0x57, 0x83), 0xc1);
+        // x^4 + x^2 + 1 = (x^2 + x + 1)^2
+        assert!("gf(2^4, 0x15)".parse::<Ring>().is_err());
+        let ring: Ring = "gf(2^3, 0xb)".parse().unwrap();
+        let isw = build_word_isw(ring, 2);
+        for x in 0..64 {
+            let (x0, x1, y0, y1) = (x % 8, x / 8, (x + 3) % 8, (5 * x) % 8);
+            let out = isw.eval(&[x0, x1, y0, y1], &[x % 7]).unwrap();
+            assert_eq!(
+                ring.unmask(Sharing::Boolean, &out),
+                ring.mul(x0 ^ x1, y0 ^ y1)
+            );
+        }
+        for probe in 0..isw.vars.len() {
+            let exact = isw.sim_set(&[probe], WordSimMethod::Exhaustive).unwrap();
+            let heuristic = isw.sim_set(&[probe], WordSimMethod::Heuristic).unwrap();
+            assert!(exact.iter().all(|x| heuristic.contains(x)));
+            assert!(exact.len() <= 2, "probe {}: {:?}", probe, exact);
+        }
+        let square = build_word_square(ring, 2).unwrap();
+        assert_eq!(
+            square.eval(&[3, 5], &[]),
+            Ok(vec![ring.mul(3, 3), ring.mul(5, 5)])
+        );
+        assert!(build_word_square(Ring::Prime(5), 2).is_err());
+        // Masking by the square or an invertible affine map of a random.
+        let masked = |src| {
+            WordCircuit::new(
+                ring,
+                vec![
+                    WordVar::new(WordSrc::Input(0, 0), Some((0, 0)), "a0"),
+                    WordVar::new(WordSrc::Input(0, 1), None, "a1"),
+                    WordVar::new(WordSrc::Random, None, "r"),
+                    WordVar::new(src, None, "s"),
+                    WordVar::new(WordSrc::Xor([1, 3]), Some((0, 1)), "b1"),
+                ],
+                2,
+                vec![Sharing::Boolean],
+                vec![Sharing::Boolean],
+            )
+            .unwrap()
+        };
+        for (src, sim) in [
+            (WordSrc::FieldSquare([2]), vec![]),
+            (WordSrc::Affine([2], vec![0b011, 0b010, 0b100], 5), vec![]),
+            (WordSrc::Linear([2], vec![0b011, 0b011, 0b100]), vec![1]),
+        ] {
+            let circ = masked(src);
+            assert_eq!(circ.sim_set(&[4], WordSimMethod::Heuristic), Ok(sim));
+        }
+    }
+}
@@ -14,8 +14,13 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+// The `#[pymethods]` expansion of pyo3 0.22 converts the `PyErr` of each `PyResult` method into
+// itself, which clippy reports as a useless conversion.
+#![allow(clippy::useless_conversion)]
+
 use numpy::{PyArray2, PyArray3, PyArrayMethods, ToPyArray};
 use pyo3::prelude::*;
+use pyo3::types::PyDict;
 use std::convert::TryInto;
 
 pub mod circuit;
@@ -40,20 +45,29 @@ macro_rules! py_type_wrapper {
     };
 }
 
-py_type_wrapper!(pd::CntSim<pd::SimGadget>, PyCntSim);
 py_type_wrapper!(pd::CntSimSt, PyCntSimSt);
 py_type_wrapper!(pd::SampleRes, PySampleRes);
 py_type_wrapper!(pd::GPdt, PyGPdt);
 py_type_wrapper!(ndarray::Array2<f64>, PyPDT);
 py_type_wrapper!(circuit::SlSharedCircuit, PyCompGraph);
+py_type_wrapper!(circuit::WordCircuit, PyWordCircuit);
 py_type_wrapper!(
     std::sync::RwLock<pd::ProbeDistribution<String>>,
     PyProbeDistribution
 );
 
+/// Sampler, with its settings (circuit fingerprint, `use_copy` and leakage model), which are
+/// recorded in stored files and checkpoints.
+#[pyclass]
+struct PyCntSim {
+    inner: pd::CntSim<pd::AnyGadget>,
+    metadata: pd::Metadata,
+}
+
 #[pymodule]
 fn _straps_ext(_py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<PyCompGraph>()?;
+    m.add_class::<PyWordCircuit>()?;
     m.add_class::<PyCntSim>()?;
     m.add_class::<PyCntSimSt>()?;
     m.add_class::<PyProbeDistribution>()?;
@@ -75,6 +89,11 @@ impl<'a> std::convert::From<&'a str> for SErr {
     }
 }
 
+/// Names of the probes, outputs and required inputs of a `circuit::Counterexample`.
+type PyCounterexample = (Vec<String>, Vec<String>, Vec<String>);
+/// Output port, output share, ones and value of a `circuit::Distinguisher`.
+type PyDistinguisher = (usize, Option<usize>, Vec<String>, bool);
+
 #[pymethods]
 impl PyCompGraph {
     #[new]
@@ -91,13 +110,276 @@ impl PyCompGraph {
             circuit::new_sl_sc(vars, n_shares, n_input_ports, n_output_ports).map_err(SErr)?;
         Ok(comp_graph.into())
     }
-    fn sim_set(&self, probes: Vec<u32>) -> Vec<u32> {
-        circuit::sim_set(&self.inner, probes)
+    #[staticmethod]
+    fn from_ironmask(text: &str) -> PyResult<PyCompGraph> {
+        let comp_graph = circuit::parse_ironmask(text).map_err(SErr)?;
+        Ok(comp_graph.into())
+    }
+    #[staticmethod]
+    fn from_bristol(
+        text: &str,
+        inputs: Vec<Vec<usize>>,
+        outputs: Vec<Vec<usize>>,
+        randoms: Vec<usize>,
+    ) -> PyResult<PyCompGraph> {
+        let roles = circuit::BristolRoles {
+            inputs,
+            outputs,
+            randoms,
+        };
+        let comp_graph = circuit::parse_bristol(text, &roles).map_err(SErr)?;
+        Ok(comp_graph.into())
+    }
+    /// `spec` is the port annotation file (see `circuit::PortSpec`).
+    #[staticmethod]
+    fn from_aiger(text: &str, spec: &str) -> PyResult<PyCompGraph> {
+        let spec = circuit::PortSpec::parse(spec).map_err(SErr)?;
+        let comp_graph = circuit::parse_aiger(text, &spec).map_err(SErr)?;
+        Ok(comp_graph.into())
+    }
+    /// `spec` is the port annotation file (see `circuit::PortSpec`).
+    #[staticmethod]
+    fn from_blif(text: &str, spec: &str) -> PyResult<PyCompGraph> {
+        let spec = circuit::PortSpec::parse(spec).map_err(SErr)?;
+        let comp_graph = circuit::parse_blif(text, &spec).map_err(SErr)?;
+        Ok(comp_graph.into())
+    }
+    /// Without `spec`, port roles are inferred from attributes or names (see
+    /// `circuit::parse_verilog`).
+    #[staticmethod]
+    #[pyo3(signature = (text, spec=None))]
+    fn from_verilog(text: &str, spec: Option<&str>) -> PyResult<PyCompGraph> {
+        let spec = spec
+            .map(circuit::PortSpec::parse)
+            .transpose()
+            .map_err(SErr)?;
+        let comp_graph = circuit::parse_verilog(text, spec.as_ref()).map_err(SErr)?;
+        Ok(comp_graph.into())
+    }
+    /// Graphviz rendering, optionally coloring each variable by a score.
+    #[pyo3(signature = (scores=None))]
+    fn to_dot(&self, scores: Option<Vec<f64>>) -> PyResult<String> {
+        Ok(self.inner.to_dot(scores.as_deref()).map_err(SErr)?)
+    }
+    fn var_use_counts(&self) -> Vec<u32> {
+        self.inner.var_use_counts()
+    }
+    /// Same circuit with gates of more than two operands replaced by balanced trees of 2-input
+    /// gates.
+    fn binarize(&self) -> PyResult<PyCompGraph> {
+        let (circ, _) = self.inner.binarize().map_err(SErr)?;
+        Ok(circ.into())
+    }
+    /// `method` is "heuristic" or "linalg" (see `circuit::SimMethod`).
+    #[pyo3(signature = (probes, method="heuristic"))]
+    fn sim_set(&self, probes: Vec<u32>, method: &str) -> PyResult<Vec<u32>> {
+        let method = method.parse().map_err(SErr)?;
+        Ok(circuit::sim_set_with(&self.inner, probes, method))
+    }
+    /// First counterexample (probes, outputs, required inputs) to the t-`property` ("NI", "SNI"
+    /// or "PINI"), or None if the circuit satisfies it.
+    #[pyo3(signature = (property, t, method="heuristic"))]
+    fn check_probing(
+        &self,
+        py: Python,
+        property: &str,
+        t: usize,
+        method: &str,
+    ) -> PyResult<Option<PyCounterexample>> {
+        let property = property.parse().map_err(SErr)?;
+        let method = method.parse().map_err(SErr)?;
+        Ok(py
+            .allow_threads(|| circuit::check_probing(&self.inner, property, t, method))
+            .map(|c| (c.probes, c.outputs, c.inputs)))
+    }
+    /// Check that each output sharing computes the given function of the unmasked inputs, given
+    /// by exactly one of: its ANF (per output, the monomials as lists of input ports), its truth
+    /// table (per output, indexed by the input bits) or an unmasked (single-share) circuit.
+    /// Returns None if correct, else (output port, input shares and randoms set to 1, expected
+    /// output).
+    #[pyo3(signature = (anf=None, truth_table=None, circuit=None))]
+    fn check_function(
+        &self,
+        anf: Option<Vec<Vec<Vec<usize>>>>,
+        truth_table: Option<Vec<Vec<bool>>>,
+        circuit: Option<PyRef<PyCompGraph>>,
+    ) -> PyResult<Option<(usize, Vec<String>, bool)>> {
+        let spec = match (anf, truth_table, circuit) {
+            (Some(anf), None, None) => circuit::FunctionSpec::Anf(anf),
+            (None, Some(table), None) => circuit::FunctionSpec::TruthTable(table),
+            (None, None, Some(circ)) => circuit::FunctionSpec::Circuit(circ.inner.clone()),
+            _ => {
+                return Err(SErr(
+                    "exactly one of anf, truth_table and circuit must be given".to_owned(),
+                )
+                .into())
+            }
+        };
+        Ok(self
+            .inner
+            .check_function(&spec)
+            .map_err(SErr)?
+            .map(|m| (m.output_port, m.ones, m.expected)))
+    }
+    /// Uniformity of the output sharings (exhaustive, for small gadgets). Returns the failures
+    /// as (output shares whose sum is biased, unmasked inputs, probability that the sum is 1).
+    /// With `joint`, the output sharings must also be jointly uniform.
+    #[pyo3(signature = (joint=false))]
+    fn check_uniformity(
+        &self,
+        py: Python,
+        joint: bool,
+    ) -> PyResult<Vec<(Vec<String>, usize, f64)>> {
+        let failures = py
+            .allow_threads(|| self.inner.check_uniformity(joint))
+            .map_err(SErr)?;
+        Ok(failures
+            .into_iter()
+            .map(|f| (f.shares, f.secret, f.p_one))
+            .collect())
+    }
+    /// `order`-th order non-completeness of the component functions of each register stage.
+    /// The sharings are the inputs, then `register_sharings` (lists of register variable ids,
+    /// one per share), which are required if some registers are not registers of input shares
+    /// (those stand for the input shares). Returns the minimal violations as (stage, wire names,
+    /// sharing of which they depend on all the shares).
+    #[pyo3(signature = (order=1, register_sharings=None))]
+    fn check_non_completeness(
+        &self,
+        order: usize,
+        register_sharings: Option<Vec<Vec<usize>>>,
+    ) -> PyResult<Vec<(usize, Vec<String>, usize)>> {
+        Ok(self
+            .inner
+            .check_non_completeness(order, register_sharings.as_deref())
+            .map_err(SErr)?
+            .into_iter()
+            .map(|v| (v.stage, v.wires, v.sharing))
+            .collect())
+    }
+    /// Whether `other` computes the same output shares ("shares" `mode`) or the same unmasked
+    /// functions ("unmasked" `mode`). Input (resp. output) port `p` of `other` is port
+    /// `input_map[p]` (resp. `output_map[p]`), by default the identity. Returns None if
+    /// equivalent, else (output port, output share or None, input shares and randoms set to 1,
+    /// value in this circuit).
+    #[pyo3(signature = (other, input_map=None, output_map=None, mode="shares"))]
+    fn equivalent(
+        &self,
+        other: PyRef<PyCompGraph>,
+        input_map: Option<Vec<usize>>,
+        output_map: Option<Vec<usize>>,
+        mode: &str,
+    ) -> PyResult<Option<PyDistinguisher>> {
+        let mode = mode.parse().map_err(SErr)?;
+        let port_map = circuit::PortMap {
+            inputs: input_map.unwrap_or_else(|| (0..other.inner.n_input_ports).collect()),
+            outputs: output_map.unwrap_or_else(|| (0..other.inner.n_output_ports).collect()),
+        };
+        Ok(self
+            .inner
+            .equivalent(&other.inner, &port_map, mode)
+            .map_err(SErr)?
+            .map(|d| (d.output_port, d.share, d.ones, d.value)))
+    }
+    /// Exact (exhaustive) version of `sim_set`, for small circuits.
+    fn sim_set_exhaustive(&self, py: Python, probes: Vec<u32>) -> PyResult<Vec<u32>> {
+        py.allow_threads(|| Ok(circuit::sim_set_exhaustive(&self.inner, probes).map_err(SErr)?))
     }
 
-    fn cnt_sim(&self, use_copy: bool) -> PyCntSim {
+    /// With `glitches`, probes are glitch-extended (robust probing model).
+    /// With `exec_order`, a list of (variable, storage slot) in execution order, probes also
+    /// reveal the value overwritten in the slot (transition leakage).
+    /// `sim_method` is the simulatability algorithm (see `sim_set`).
+    /// With `binarize`, gates with more than two operands leak as balanced trees of 2-input
+    /// gates, otherwise as a single wire.
+    /// With `gate_leakage`, probes are on the gates (sum, product and not), and reveal their
+    /// output and operands (`use_copy` is then irrelevant).
+    /// With `coupling`, probes also reveal the neighbours of the wire (see `set_adjacency`),
+    /// which are glitch-extended with `glitches`.
+    #[pyo3(signature = (use_copy, glitches=false, exec_order=None, sim_method="heuristic", binarize=false, gate_leakage=false, coupling=false))]
+    #[allow(clippy::too_many_arguments)]
+    fn cnt_sim(
+        &self,
+        use_copy: bool,
+        glitches: bool,
+        exec_order: Option<Vec<(usize, usize)>>,
+        sim_method: &str,
+        binarize: bool,
+        gate_leakage: bool,
+        coupling: bool,
+    ) -> PyResult<PyCntSim> {
+        let metadata = pd::Metadata {
+            circuit: Some(self.inner.fingerprint()),
+            n_shares: Some(self.inner.n_shares as u64),
+            use_copy: Some(use_copy),
+            glitches: Some(glitches),
+            exec_order: exec_order.as_ref().map(|order| {
+                order
+                    .iter()
+                    .map(|(var, slot)| (*var as u64, *slot as u64))
+                    .collect()
+            }),
+            gate_leakage: Some(gate_leakage),
+            coupling: Some(coupling),
+            binarize: Some(binarize),
+            sim_method: Some(sim_method.to_owned()),
+            ..Default::default()
+        };
+        let sim_method = sim_method.parse().map_err(SErr)?;
+        let (circ, exec_order) = if binarize {
+            let (circ, map) = self.inner.binarize().map_err(SErr)?;
+            let exec_order = exec_order
+                .map(|order| {
+                    order
+                        .into_iter()
+                        .map(|(var, slot)| {
+                            map.get(var)
+                                .map(|v| (*v, slot))
+                                .ok_or_else(|| SErr(format!("variable {} does not exist", var)))
+                        })
+                        .collect::<Result<Vec<_>, _>>()
+                })
+                .transpose()?;
+            (circ, exec_order)
+        } else {
+            (self.inner.clone(), exec_order)
+        };
         let uc2mp = |uc| if use_copy { 2 * uc - 1 } else { uc };
-        pd::CntSim::new(pd::SimGadget::new(self.inner.clone(), uc2mp)).into()
+        let model = match (glitches, exec_order, gate_leakage, coupling) {
+            (false, None, false, false) => pd::ProbeModel::Value,
+            (true, None, false, false) => pd::ProbeModel::Glitch,
+            (false, Some(order), false, false) => {
+                pd::ProbeModel::Transition(circ.transitions(&order).map_err(SErr)?)
+            }
+            (false, None, true, false) => pd::ProbeModel::Gate,
+            (glitches, None, false, true) => pd::ProbeModel::Coupling { glitches },
+            _ => {
+                return Err(SErr(
+                    "transitions, gate leakage and coupling cannot be combined".to_owned(),
+                )
+                .into())
+            }
+        };
+        let gadget = pd::SimGadget::new(circ, uc2mp, model, sim_method);
+        Ok(PyCntSim {
+            inner: pd::CntSim::new(pd::AnyGadget::Bit(gadget)),
+            metadata,
+        })
+    }
+    /// Physical neighbourhood of the wires, as a list of pairs of adjacent variables (indices),
+    /// used by `cnt_sim(..., coupling=True)`.
+    fn set_adjacency(&mut self, edges: Vec<(usize, usize)>) -> PyResult<()> {
+        Ok(self.inner.set_adjacency(&edges).map_err(SErr)?)
+    }
+    fn neighbours(&self, var: usize) -> PyResult<Vec<usize>> {
+        if var >= self.inner.vars.len() {
+            return Err(SErr(format!("variable {} does not exist", var)).into());
+        }
+        Ok(self.inner.neighbours(var).to_vec())
+    }
+    /// Fingerprint of the circuit, to be stored in the metadata of saved PDTs.
+    fn fingerprint(&self) -> String {
+        self.inner.fingerprint()
     }
     fn output_ports(&self) -> Vec<Option<(usize, usize)>> {
         self.inner.vars.iter().map(|v| v.output_port).collect()
@@ -140,6 +422,14 @@ impl PyCompGraph {
     const VAR_KIND_PRODUCT: u32 = 3;
     #[classattr]
     const VAR_KIND_NOT: u32 = 4;
+    #[classattr]
+    const VAR_KIND_REG: u32 = 5;
+    #[classattr]
+    const VAR_KIND_ZERO: u32 = 6;
+    #[classattr]
+    const VAR_KIND_ONE: u32 = 7;
+    #[classattr]
+    const VAR_KIND_PUBLIC: u32 = 8;
     fn var_kind(&self, i: usize) -> u32 {
         match self.inner.vars[i].src {
             circuit::VarSrc::Input(_, _) => Self::VAR_KIND_INPUT,
@@ -147,6 +437,10 @@ impl PyCompGraph {
             circuit::VarSrc::Sum(_) => Self::VAR_KIND_SUM,
             circuit::VarSrc::Product(_) => Self::VAR_KIND_PRODUCT,
             circuit::VarSrc::Not(_) => Self::VAR_KIND_NOT,
+            circuit::VarSrc::Reg(_) => Self::VAR_KIND_REG,
+            circuit::VarSrc::Const(false) => Self::VAR_KIND_ZERO,
+            circuit::VarSrc::Const(true) => Self::VAR_KIND_ONE,
+            circuit::VarSrc::Public(_) => Self::VAR_KIND_PUBLIC,
         }
     }
     fn var_inputs(&self, i: usize) -> Vec<usize> {
@@ -160,6 +454,102 @@ impl PyCompGraph {
     }
 }
 
+#[pymethods]
+impl PyWordCircuit {
+    /// `ring` is "mod(2^k)", "gf(p)" or "gf(2^n, poly)" (poly defaults to the AES polynomial for
+    /// n = 8). Each variable is (kind, operands, name, parameters), where kind is one of "input",
+    /// "random", "const" (whose value is the only parameter), "add", "sub", "mul" (ring
+    /// operations), "mul_const" (multiplication by the parameter), "square", "linear" and
+    /// "affine" (whose parameters are the rows of the GF(2) matrix, followed by the constant for
+    /// "affine"), "xor" and "and" (bitwise operations, not in GF(p)). `input_sharings` and
+    /// `output_sharings` give the kind ("boolean" or "arithmetic") of each port.
+    #[new]
+    fn new(
+        ring: &str,
+        vars: Vec<(String, Vec<usize>, String, Vec<u64>)>,
+        in_ports: Vec<Option<(usize, usize)>>,
+        out_ports: Vec<Option<(usize, usize)>>,
+        n_shares: usize,
+        input_sharings: Vec<String>,
+        output_sharings: Vec<String>,
+    ) -> PyResult<PyWordCircuit> {
+        let ring = ring.parse().map_err(SErr)?;
+        let parse_sharings = |sharings: Vec<String>| {
+            sharings
+                .iter()
+                .map(|s| s.parse())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(SErr)
+        };
+        let circ = circuit::WordCircuit::new(
+            ring,
+            vecs2words(vars, in_ports, out_ports)?,
+            n_shares,
+            parse_sharings(input_sharings)?,
+            parse_sharings(output_sharings)?,
+        )
+        .map_err(SErr)?;
+        Ok(circ.into())
+    }
+    /// ISW multiplication of two arithmetic sharings in `ring`.
+    #[staticmethod]
+    fn isw(ring: &str, n_shares: usize) -> PyResult<PyWordCircuit> {
+        let ring = ring.parse().map_err(SErr)?;
+        Ok(circuit::build_word_isw(ring, n_shares).into())
+    }
+    /// Sharewise square in `ring` = "gf(2^n, poly)".
+    #[staticmethod]
+    fn square(ring: &str, n_shares: usize) -> PyResult<PyWordCircuit> {
+        let ring = ring.parse().map_err(SErr)?;
+        Ok(circuit::build_word_square(ring, n_shares)
+            .map_err(SErr)?
+            .into())
+    }
+    /// Input shares required to simulate the probes (variable indices). `method` is "heuristic"
+    /// or "exhaustive" (exact, for small rings).
+    #[pyo3(signature = (probes, method="heuristic"))]
+    fn sim_set(&self, probes: Vec<usize>, method: &str) -> PyResult<Vec<usize>> {
+        let method = method.parse().map_err(SErr)?;
+        Ok(self.inner.sim_set(&probes, method).map_err(SErr)?)
+    }
+    /// Output shares for the given input shares (by port, then share) and randoms (in variable
+    /// order).
+    fn eval(&self, input_shares: Vec<u64>, randoms: Vec<u64>) -> PyResult<Vec<u64>> {
+        Ok(self.inner.eval(&input_shares, &randoms).map_err(SErr)?)
+    }
+    /// `sim_method` is the simulatability algorithm (see `sim_set`).
+    #[pyo3(signature = (use_copy, sim_method="heuristic"))]
+    fn cnt_sim(&self, use_copy: bool, sim_method: &str) -> PyResult<PyCntSim> {
+        let metadata = pd::Metadata {
+            circuit: Some(self.inner.fingerprint()),
+            n_shares: Some(self.inner.n_shares as u64),
+            use_copy: Some(use_copy),
+            sim_method: Some(sim_method.to_owned()),
+            ..Default::default()
+        };
+        let sim_method = sim_method.parse().map_err(SErr)?;
+        let uc2mp = |uc| if use_copy { 2 * uc - 1 } else { uc };
+        let gadget = pd::WordGadget::new(self.inner.clone(), uc2mp, sim_method).map_err(SErr)?;
+        Ok(PyCntSim {
+            inner: pd::CntSim::new(pd::AnyGadget::Word(gadget)),
+            metadata,
+        })
+    }
+    fn name(&self, i: usize) -> String {
+        self.inner.vars[i].name.clone()
+    }
+    fn n_vars(&self) -> usize {
+        self.inner.vars.len()
+    }
+    fn n_shares(&self) -> usize {
+        self.inner.n_shares
+    }
+    /// Fingerprint of the circuit, to be stored in the metadata of saved PDTs.
+    fn fingerprint(&self) -> String {
+        self.inner.fingerprint()
+    }
+}
+
 #[pymethods]
 impl PySampleRes {
     fn n_probes(&self) -> usize {
@@ -174,18 +564,66 @@ impl PySampleRes {
 }
 #[pymethods]
 impl PyCntSim {
-    fn run_sampling(&self, py: Python, n_s_max: u32, suff_thresh: u32) -> PyCntSimSt {
-        py.allow_threads(|| {
+    /// Settings of the sampler (circuit fingerprint, number of shares, `use_copy` and leakage
+    /// model), as keyword arguments of `PyCntSimSt.save`.
+    fn metadata<'p>(&self, py: Python<'p>) -> PyResult<Bound<'p, PyDict>> {
+        let res = PyDict::new_bound(py);
+        for (key, value) in metadata2dict(py, &self.metadata)?.iter() {
+            if !value.is_none() && key.extract::<String>()? != "straps_version" {
+                res.set_item(key, value)?;
+            }
+        }
+        Ok(res)
+    }
+    /// With `seed`, the result is reproducible. With `checkpoint` (a file path), the finished
+    /// columns are saved every `checkpoint_interval` seconds, and a run interrupted with the same
+    /// checkpoint (by a sampler with the same `metadata`) is resumed (with the seed stored in the
+    /// checkpoint, if `seed` is None). The seed used is given by `seed` on the result.
+    #[pyo3(signature = (n_s_max, suff_thresh, seed=None, checkpoint=None, checkpoint_interval=60.0))]
+    fn run_sampling(
+        &self,
+        py: Python,
+        n_s_max: u32,
+        suff_thresh: u32,
+        seed: Option<u64>,
+        checkpoint: Option<&str>,
+        checkpoint_interval: f64,
+    ) -> PyResult<PyCntSimSt> {
+        let mut checkpoint = checkpoint
+            .map(|path| {
+                pd::Checkpoint::open(
+                    path,
+                    std::time::Duration::from_secs_f64(checkpoint_interval),
+                    &self.metadata,
+                )
+            })
+            .transpose()
+            .map_err(SErr)?;
+        let res = py.allow_threads(|| {
             let firestorm_dir = std::env::var("STRAPS_FIRESTORM_DIR");
             if firestorm_dir.is_ok() {
                 firestorm::clear();
             }
-            let res = self.inner.run_sampling(n_s_max, suff_thresh).into();
+            let res = self
+                .inner
+                .run_sampling(n_s_max, suff_thresh, seed, checkpoint.as_mut());
             if let Ok(firestorm_dir) = firestorm_dir {
                 firestorm::save(firestorm_dir).unwrap();
             }
             res
-        })
+        });
+        Ok(res.map_err(SErr)?.into())
+    }
+    /// Like `run_sampling`, with one probe count per probe class (see `class_sizes`): the first
+    /// axis of the result is the flattened per-class probe counts, the last class varying
+    /// fastest.
+    fn run_sampling_classes(&self, py: Python, n_s_max: u32, suff_thresh: u32) -> PyCntSimSt {
+        py.allow_threads(|| self.inner.run_sampling_classes(n_s_max, suff_thresh).into())
+    }
+    /// Number of probes in each class: inputs, randoms, linear gates (XOR, NOT, registers,
+    /// additions) and non-linear gates (AND, multiplications).
+    fn class_sizes(&self) -> Vec<u32> {
+        self.inner.class_sizes()
     }
     fn probe_output(
         &self,
@@ -199,24 +637,76 @@ impl PyCntSim {
                 .template("{msg} [{bar:40}] {pos}/{len} [{elapsed_precise}>{eta_precise}]");
             pd::multiprogress::MultiProgressConfig::new(1, style).run(|mp| {
                 let sub_progress = mp.sub(0);
+                let done = std::collections::HashSet::new();
                 self.inner
-                    .probe_output(output_id, n_s_max, suff_thresh, sub_progress)
+                    .probe_output(
+                        output_id,
+                        n_s_max,
+                        suff_thresh,
+                        rand::random(),
+                        &done,
+                        sub_progress,
+                    )
                     .map(|x| x.into())
                     .collect::<Vec<_>>()
             })
         })
     }
+    /// For each output subset, the smallest leaking probe sets (as lists of variable names) and
+    /// the number of leaking probe sets of each size, up to `max_size` probed variables.
+    fn min_leaking_sets(&self, py: Python, max_size: usize) -> Vec<(Vec<Vec<String>>, Vec<u64>)> {
+        py.allow_threads(|| {
+            self.inner
+                .min_leaking_sets(max_size)
+                .into_iter()
+                .map(|l| (l.min_sets, l.counts))
+                .collect()
+        })
+    }
 }
 
 #[pymethods]
 impl PyCntSimSt {
+    /// `class_sizes` is the number of probes of each class (see `PyCntSim.class_sizes`), by
+    /// default there is a single class.
     #[new]
-    fn new(counts: &Bound<'_, PyArray3<u64>>, exhaustive: &Bound<'_, PyArray2<bool>>) -> Self {
-        pd::CntSimSt {
-            cnt: counts.to_owned_array(),
-            exhaustive: exhaustive.to_owned_array(),
-        }
-        .into()
+    #[pyo3(signature = (counts, exhaustive, class_sizes=None))]
+    fn new(
+        counts: &Bound<'_, PyArray3<u64>>,
+        exhaustive: &Bound<'_, PyArray2<bool>>,
+        class_sizes: Option<Vec<u32>>,
+    ) -> PyResult<Self> {
+        let st = pd::CntSimSt::from_arrays(
+            counts.to_owned_array(),
+            exhaustive.to_owned_array(),
+            class_sizes,
+        )
+        .map_err(SErr)?;
+        Ok(st.into())
+    }
+    fn class_sizes(&self) -> Vec<u32> {
+        self.inner.class_sizes.clone()
+    }
+    /// Seed of the sampling (see `PyCntSim.run_sampling`), None if unknown.
+    fn seed(&self) -> Option<u64> {
+        self.inner.seed
+    }
+    /// Write to `path` in the STRAPS binary format, `metadata` are the keyword arguments
+    /// `circuit` (fingerprint), `n_shares`, `n_s_max`, `suff_thresh`, `use_copy`, `seed` and the
+    /// leakage model settings of `PyCompGraph.cnt_sim` (`glitches`, `exec_order`,
+    /// `gate_leakage`, `coupling`, `binarize` and `sim_method`).
+    #[pyo3(signature = (path, **metadata))]
+    fn save(&self, path: &str, metadata: Option<&Bound<'_, PyDict>>) -> PyResult<()> {
+        let metadata = dict2metadata(metadata)?;
+        let mut f = create_file(path)?;
+        self.inner.write(&mut f, &metadata).map_err(SErr)?;
+        Ok(())
+    }
+    /// Read a file written by `save`, returns the object and its metadata (as a dict).
+    #[staticmethod]
+    fn load<'p>(py: Python<'p>, path: &str) -> PyResult<(Self, Bound<'p, PyDict>)> {
+        let (st, metadata) = pd::CntSimSt::read(&mut open_file(path)?).map_err(SErr)?;
+        Ok((st.into(), metadata2dict(py, &metadata)?))
     }
     fn estimate(&self, py: Python) -> PyGPdt {
         py.allow_threads(|| self.inner.estimate().into())
@@ -300,6 +790,20 @@ impl PyPDT {
     fn from_array(array: &Bound<'_, PyArray2<f64>>) -> Self {
         array.to_owned_array().into()
     }
+    /// See `PyCntSimSt.save`.
+    #[pyo3(signature = (path, **metadata))]
+    fn save(&self, path: &str, metadata: Option<&Bound<'_, PyDict>>) -> PyResult<()> {
+        let metadata = dict2metadata(metadata)?;
+        let mut f = create_file(path)?;
+        pd::write_pdt(&mut f, &self.inner, &metadata).map_err(SErr)?;
+        Ok(())
+    }
+    /// See `PyCntSimSt.load`.
+    #[staticmethod]
+    fn load<'p>(py: Python<'p>, path: &str) -> PyResult<(Self, Bound<'p, PyDict>)> {
+        let (pdt, metadata) = pd::read_pdt(&mut open_file(path)?).map_err(SErr)?;
+        Ok((pdt.into(), metadata2dict(py, &metadata)?))
+    }
 }
 
 #[pymethods]
@@ -307,11 +811,86 @@ impl PyGPdt {
     fn instantiate(&self, py: Python, p: f64) -> PyPDT {
         py.allow_threads(|| self.inner.instantiate(p)).into()
     }
+    /// PDT where the probes of each class leak with the corresponding probability.
+    fn instantiate_classes(&self, py: Python, ps: Vec<f64>) -> PyResult<PyPDT> {
+        let pdt = py
+            .allow_threads(|| self.inner.instantiate_classes(&ps))
+            .map_err(SErr)?;
+        Ok(pdt.into())
+    }
+    fn class_sizes(&self) -> Vec<u32> {
+        self.inner.class_sizes().to_vec()
+    }
+    /// See `PyCntSimSt.save`.
+    #[pyo3(signature = (path, **metadata))]
+    fn save(&self, path: &str, metadata: Option<&Bound<'_, PyDict>>) -> PyResult<()> {
+        let metadata = dict2metadata(metadata)?;
+        let mut f = create_file(path)?;
+        self.inner.write(&mut f, &metadata).map_err(SErr)?;
+        Ok(())
+    }
+    /// See `PyCntSimSt.load`.
+    #[staticmethod]
+    fn load<'p>(py: Python<'p>, path: &str) -> PyResult<(Self, Bound<'p, PyDict>)> {
+        let (gpdt, metadata) = pd::GPdt::read(&mut open_file(path)?).map_err(SErr)?;
+        Ok((gpdt.into(), metadata2dict(py, &metadata)?))
+    }
     fn to_array<'p>(&self, py: Python<'p>) -> Bound<'p, PyArray3<f64>> {
         self.inner.as_ratios().to_pyarray_bound(py)
     }
 }
 
+fn create_file(path: &str) -> Result<std::io::BufWriter<std::fs::File>, SErr> {
+    let f = std::fs::File::create(path).map_err(|e| SErr(format!("{}: {}", path, e)))?;
+    Ok(std::io::BufWriter::new(f))
+}
+
+fn open_file(path: &str) -> Result<std::io::BufReader<std::fs::File>, SErr> {
+    let f = std::fs::File::open(path).map_err(|e| SErr(format!("{}: {}", path, e)))?;
+    Ok(std::io::BufReader::new(f))
+}
+
+fn dict2metadata(dict: Option<&Bound<'_, PyDict>>) -> PyResult<pd::Metadata> {
+    let mut metadata = pd::Metadata::default();
+    for (key, value) in dict.into_iter().flat_map(|d| d.iter()) {
+        let key: String = key.extract()?;
+        match key.as_str() {
+            "circuit" => metadata.circuit = value.extract()?,
+            "n_shares" => metadata.n_shares = value.extract()?,
+            "n_s_max" => metadata.n_s_max = value.extract()?,
+            "suff_thresh" => metadata.suff_thresh = value.extract()?,
+            "use_copy" => metadata.use_copy = value.extract()?,
+            "seed" => metadata.seed = value.extract()?,
+            "glitches" => metadata.glitches = value.extract()?,
+            "exec_order" => metadata.exec_order = value.extract()?,
+            "gate_leakage" => metadata.gate_leakage = value.extract()?,
+            "coupling" => metadata.coupling = value.extract()?,
+            "binarize" => metadata.binarize = value.extract()?,
+            "sim_method" => metadata.sim_method = value.extract()?,
+            _ => return Err(SErr(format!("Unknown metadata key '{}'", key)).into()),
+        }
+    }
+    Ok(metadata)
+}
+
+fn metadata2dict<'p>(py: Python<'p>, metadata: &pd::Metadata) -> PyResult<Bound<'p, PyDict>> {
+    let dict = PyDict::new_bound(py);
+    dict.set_item("circuit", &metadata.circuit)?;
+    dict.set_item("n_shares", metadata.n_shares)?;
+    dict.set_item("n_s_max", metadata.n_s_max)?;
+    dict.set_item("suff_thresh", metadata.suff_thresh)?;
+    dict.set_item("use_copy", metadata.use_copy)?;
+    dict.set_item("seed", metadata.seed)?;
+    dict.set_item("glitches", metadata.glitches)?;
+    dict.set_item("exec_order", &metadata.exec_order)?;
+    dict.set_item("gate_leakage", metadata.gate_leakage)?;
+    dict.set_item("coupling", metadata.coupling)?;
+    dict.set_item("binarize", metadata.binarize)?;
+    dict.set_item("sim_method", &metadata.sim_method)?;
+    dict.set_item("straps_version", &metadata.straps_version)?;
+    Ok(dict)
+}
+
 fn vecs2graph(
     vars: Vec<(u32, Vec<usize>, String)>,
     in_ports: Vec<Option<(usize, usize)>>,
@@ -329,15 +908,20 @@ fn vecs2graph(
                         return Err(SErr("Input has no port".to_owned()));
                     }
                     (1, None) => circuit::VarSrc::Random,
-                    (2, None) => {
-                        circuit::VarSrc::Sum(ops.try_into().map_err(|_| "Wrong ops count")?)
-                    }
-                    (3, None) => {
-                        circuit::VarSrc::Product(ops.try_into().map_err(|_| "Wrong ops count")?)
+                    (2, None) | (3, None) if ops.len() < 2 => {
+                        return Err(SErr("Wrong ops count".to_owned()));
                     }
+                    (2, None) => circuit::VarSrc::Sum(ops),
+                    (3, None) => circuit::VarSrc::Product(ops),
                     (4, None) => {
                         circuit::VarSrc::Not(ops.try_into().map_err(|_| "Wrong ops count")?)
                     }
+                    (5, None) => {
+                        circuit::VarSrc::Reg(ops.try_into().map_err(|_| "Wrong ops count")?)
+                    }
+                    (6, None) => circuit::VarSrc::Const(false),
+                    (7, None) => circuit::VarSrc::Const(true),
+                    (8, None) => circuit::VarSrc::Public(name.clone()),
                     (_, p) => {
                         return Err(SErr(format!("Invalid op kind {} or port {:?}", kind, p)));
                     }
@@ -348,3 +932,56 @@ fn vecs2graph(
         })
         .collect()
 }
+
+fn vecs2words(
+    vars: Vec<(String, Vec<usize>, String, Vec<u64>)>,
+    in_ports: Vec<Option<(usize, usize)>>,
+    out_ports: Vec<Option<(usize, usize)>>,
+) -> Result<Vec<circuit::WordVar>, SErr> {
+    vars.into_iter()
+        .zip(in_ports)
+        .zip(out_ports)
+        .map(|(((kind, ops, name, params), in_port), out_port)| {
+            let binary = || -> Result<[usize; 2], SErr> {
+                ops.as_slice()
+                    .try_into()
+                    .map_err(|_| SErr(format!("Wrong ops count for {}", name)))
+            };
+            let unary = || -> Result<[usize; 1], SErr> {
+                ops.as_slice()
+                    .try_into()
+                    .map_err(|_| SErr(format!("Wrong ops count for {}", name)))
+            };
+            let src = match (kind.as_str(), in_port, params.as_slice()) {
+                ("input", Some((p, s)), []) => circuit::WordSrc::Input(p, s),
+                ("random", None, []) => circuit::WordSrc::Random,
+                ("const", None, [c]) => circuit::WordSrc::Const(*c),
+                ("add", None, []) => circuit::WordSrc::AddMod(binary()?),
+                ("sub", None, []) => circuit::WordSrc::SubMod(binary()?),
+                ("mul", None, []) => circuit::WordSrc::MulMod(binary()?),
+                ("mul_const", None, [c]) => circuit::WordSrc::MulConst(unary()?, *c),
+                ("square", None, []) => circuit::WordSrc::FieldSquare(unary()?),
+                ("linear", None, rows) => circuit::WordSrc::Linear(unary()?, rows.to_vec()),
+                ("affine", None, [rows @ .., c]) => {
+                    circuit::WordSrc::Affine(unary()?, rows.to_vec(), *c)
+                }
+                ("xor", None, []) => circuit::WordSrc::Xor(binary()?),
+                ("and", None, []) => circuit::WordSrc::And(binary()?),
+                (_, p, params) => {
+                    return Err(SErr(format!(
+                        "Invalid op kind {}, port {:?} or parameters {:?}",
+                        kind, p, params
+                    )));
+                }
+            };
+            if src.operands() != ops.as_slice() {
+                return Err(SErr(format!("Wrong ops count for {}", name)));
+            }
+            Ok(circuit::WordVar {
+                src,
+                output_port: out_port,
+                name,
+            })
+        })
+        .collect()
+}
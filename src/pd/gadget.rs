@@ -27,6 +27,9 @@ pub(crate) trait Gadget {
     fn n_pp(&self) -> usize;
     /// Shall be sorted in decreasing order.
     fn pp_maxp(&self) -> &[u32];
+    /// Name of the probed variable.
+    fn pp_name(&self, pp: usize) -> &str;
+    fn pp_class(&self, pp: usize) -> ProbeClass;
     fn sim_probes(
         &self,
         outputs: impl IntoIterator<Item = usize>,
@@ -34,33 +37,78 @@ pub(crate) trait Gadget {
     ) -> Vec<usize>;
 }
 
+/// Kind of the variable of a probe point, each class may have its own leakage probability.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ProbeClass {
+    Input = 0,
+    Random = 1,
+    /// XOR, NOT, registers and other linear gates.
+    Linear = 2,
+    /// AND and other non-linear gates.
+    NonLinear = 3,
+}
+
+pub(crate) const N_PROBE_CLASSES: usize = 4;
+
+/// What a probe on a wire reveals.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ProbeModel {
+    /// The value of the wire.
+    Value,
+    /// Robust probing model with glitches: the registers, inputs and randoms on which the wire
+    /// combinationally depends.
+    Glitch,
+    /// Transition leakage: the value of the wire and the value it overwrites, if any (as given
+    /// by `SlSharedCircuit::transitions`).
+    Transition(Vec<Option<usize>>),
+    /// Coupling: the wire and its physical neighbours (see `SlSharedCircuit::set_adjacency`),
+    /// all glitch-extended if `glitches`.
+    Coupling { glitches: bool },
+    /// Gate leakage: probes are on the `Sum`, `Product` and `Not` gates (one probe per gate,
+    /// whatever its fanout), and reveal the output and the operands of the gate.
+    Gate,
+}
+
 #[derive(Debug, Clone)]
 pub(crate) struct SimGadget {
     circuit: circuit::SlSharedCircuit,
     max_probes: Vec<u32>,
     probes2vars: Vec<usize>,
+    /// Variables revealed by each probe.
+    probes_leakage: Vec<Vec<usize>>,
     output_vars: Vec<usize>,
+    sim_method: circuit::SimMethod,
 }
 
 impl SimGadget {
-    pub(crate) fn new(circuit: circuit::SlSharedCircuit, uc2maxp: impl Fn(u32) -> u32) -> Self {
-        let mut var_use_count = vec![0; circuit.vars.len()];
-        for var in circuit.vars.iter() {
-            match var.src {
-                circuit::VarSrc::Sum([v1, v2]) | circuit::VarSrc::Product([v1, v2]) => {
-                    var_use_count[v1] += 1;
-                    var_use_count[v2] += 1;
-                }
-                circuit::VarSrc::Not([v]) => {
-                    var_use_count[v] += 1;
-                }
-                circuit::VarSrc::Input(_, _) | circuit::VarSrc::Random => {}
-            }
-        }
-        let mut sorted_probe_vars = var_use_count
+    pub(crate) fn new(
+        circuit: circuit::SlSharedCircuit,
+        uc2maxp: impl Fn(u32) -> u32,
+        model: ProbeModel,
+        sim_method: circuit::SimMethod,
+    ) -> Self {
+        let mut sorted_probe_vars = circuit
+            .var_use_counts()
             .into_iter()
             .enumerate()
-            .filter(|(_, use_count)| *use_count != 0)
+            // Constants and public values do not leak.
+            .filter(|(v, _)| {
+                !matches!(
+                    circuit.vars[*v].src,
+                    circuit::VarSrc::Const(_) | circuit::VarSrc::Public(_)
+                )
+            })
+            .filter_map(|(v, use_count)| match &model {
+                // Overwriting a slot leaks, even if the new value is never used.
+                ProbeModel::Transition(prev) if prev[v].is_some() => Some((v, use_count.max(1))),
+                ProbeModel::Gate => match circuit.vars[v].src {
+                    circuit::VarSrc::Sum(_)
+                    | circuit::VarSrc::Product(_)
+                    | circuit::VarSrc::Not(_) => Some((v, 1)),
+                    _ => None,
+                },
+                _ => Some((v, use_count)).filter(|_| use_count != 0),
+            })
             .collect::<Vec<_>>();
         sorted_probe_vars.sort_unstable_by(|(_, uc1), (_, uc2)| uc2.cmp(uc1));
         let probes2vars = sorted_probe_vars
@@ -69,7 +117,36 @@ impl SimGadget {
             .collect::<Vec<_>>();
         let max_probes = sorted_probe_vars
             .iter()
-            .map(|(_, uc)| uc2maxp(*uc))
+            .map(|(_, uc)| match model {
+                ProbeModel::Gate => 1,
+                _ => uc2maxp(*uc),
+            })
+            .collect::<Vec<_>>();
+        let probes_leakage = probes2vars
+            .iter()
+            .map(|v| match &model {
+                ProbeModel::Value => vec![*v],
+                ProbeModel::Glitch => circuit.glitch_extended(*v),
+                ProbeModel::Transition(prev) => std::iter::once(*v).chain(prev[*v]).collect(),
+                ProbeModel::Gate => std::iter::once(*v)
+                    .chain(circuit.vars[*v].src.operands().iter().copied())
+                    .collect(),
+                ProbeModel::Coupling { glitches } => {
+                    let mut leakage = std::iter::once(*v)
+                        .chain(circuit.neighbours(*v).iter().copied())
+                        .flat_map(|w| {
+                            if *glitches {
+                                circuit.glitch_extended(w)
+                            } else {
+                                vec![w]
+                            }
+                        })
+                        .collect::<Vec<_>>();
+                    leakage.sort_unstable();
+                    leakage.dedup();
+                    leakage
+                }
+            })
             .collect::<Vec<_>>();
         let mut output_vars = circuit
             .vars
@@ -83,7 +160,9 @@ impl SimGadget {
             circuit,
             max_probes,
             probes2vars,
+            probes_leakage,
             output_vars,
+            sim_method,
         };
     }
 }
@@ -113,6 +192,20 @@ impl Gadget for SimGadget {
     fn pp_maxp(&self) -> &[u32] {
         &self.max_probes
     }
+    fn pp_name(&self, pp: usize) -> &str {
+        &self.circuit.vars[self.probes2vars[pp]].name
+    }
+    fn pp_class(&self, pp: usize) -> ProbeClass {
+        match self.circuit.vars[self.probes2vars[pp]].src {
+            circuit::VarSrc::Input(_, _) => ProbeClass::Input,
+            circuit::VarSrc::Random => ProbeClass::Random,
+            circuit::VarSrc::Sum(_) | circuit::VarSrc::Not(_) | circuit::VarSrc::Reg(_) => {
+                ProbeClass::Linear
+            }
+            circuit::VarSrc::Product(_) => ProbeClass::NonLinear,
+            circuit::VarSrc::Const(_) | circuit::VarSrc::Public(_) => unreachable!(),
+        }
+    }
     /// Numbering of inputs and outputs corresponds to the concatenation of the sharings.
     fn sim_probes(
         &self,
@@ -124,14 +217,17 @@ impl Gadget for SimGadget {
             var_mask[self.output_vars[output]] = true;
         }
         for probe in probes {
-            var_mask[self.probes2vars[probe]] = true;
+            for var in self.probes_leakage[probe].iter() {
+                var_mask[*var] = true;
+            }
         }
         let probed_vars = var_mask
             .into_iter()
             .positions(|x| x)
             .map(|i| i as u32)
             .collect::<Vec<_>>();
-        let req_inputs: Vec<u32> = circuit::sim_set(&self.circuit, probed_vars);
+        let req_inputs: Vec<u32> =
+            circuit::sim_set_with(&self.circuit, probed_vars, self.sim_method);
         let input_offset = req_inputs
             .into_iter()
             .map(|i| match self.circuit.vars[i as usize].src {
@@ -142,3 +238,212 @@ impl Gadget for SimGadget {
         return input_offset;
     }
 }
+
+/// Bit-level or word-level gadget.
+#[derive(Debug, Clone)]
+pub(crate) enum AnyGadget {
+    Bit(SimGadget),
+    Word(super::WordGadget),
+}
+
+macro_rules! any_gadget_dispatch {
+    ($self:ident, $g:ident => $e:expr) => {
+        match $self {
+            AnyGadget::Bit($g) => $e,
+            AnyGadget::Word($g) => $e,
+        }
+    };
+}
+
+impl Gadget for AnyGadget {
+    fn n_outputs(&self) -> usize {
+        any_gadget_dispatch!(self, g => g.n_outputs())
+    }
+    fn n_inputs(&self) -> usize {
+        any_gadget_dispatch!(self, g => g.n_inputs())
+    }
+    fn max_n_probes(&self) -> u32 {
+        any_gadget_dispatch!(self, g => g.max_n_probes())
+    }
+    fn n_shares(&self) -> usize {
+        any_gadget_dispatch!(self, g => g.n_shares())
+    }
+    fn n_input_sharings(&self) -> usize {
+        any_gadget_dispatch!(self, g => g.n_input_sharings())
+    }
+    fn n_output_sharings(&self) -> usize {
+        any_gadget_dispatch!(self, g => g.n_output_sharings())
+    }
+    fn n_pp(&self) -> usize {
+        any_gadget_dispatch!(self, g => g.n_pp())
+    }
+    fn pp_maxp(&self) -> &[u32] {
+        any_gadget_dispatch!(self, g => g.pp_maxp())
+    }
+    fn pp_name(&self, pp: usize) -> &str {
+        any_gadget_dispatch!(self, g => g.pp_name(pp))
+    }
+    fn pp_class(&self, pp: usize) -> ProbeClass {
+        any_gadget_dispatch!(self, g => g.pp_class(pp))
+    }
+    fn sim_probes(
+        &self,
+        outputs: impl IntoIterator<Item = usize>,
+        probes: impl IntoIterator<Item = usize>,
+    ) -> Vec<usize> {
+        any_gadget_dispatch!(self, g => g.sim_probes(outputs, probes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use circuit::{Var, VarSrc};
+
+    /// a1 + (a0 + r), where a0 + r is optionally registered.
+    fn refresh_sum(reg: bool) -> circuit::SlSharedCircuit {
+        let vars = vec![
+            Var::new(VarSrc::Input(0, 0), None, "a0"),
+            Var::new(VarSrc::Input(0, 1), None, "a1"),
+            Var::new(VarSrc::Random, None, "r"),
+            Var::new(VarSrc::Sum(vec![0, 2]), None, "t"),
+            Var::new(
+                if reg {
+                    VarSrc::Reg([3])
+                } else {
+                    VarSrc::Not([3])
+                },
+                None,
+                "s",
+            ),
+            Var::new(VarSrc::Sum(vec![1, 4]), None, "u"),
+            Var::new(VarSrc::Not([5]), Some((0, 0)), "o0"),
+            Var::new(VarSrc::Not([4]), Some((0, 1)), "o1"),
+        ];
+        circuit::new_sl_sc(vars, 2, 1, 1).unwrap()
+    }
+
+    #[test]
+    fn test_glitch_model() {
+        for (reg, model, n_inputs) in [
+            (false, ProbeModel::Value, 0),
+            (false, ProbeModel::Glitch, 2),
+            (true, ProbeModel::Value, 0),
+            (true, ProbeModel::Glitch, 1),
+        ] {
+            let gadget = SimGadget::new(
+                refresh_sum(reg),
+                |uc| uc,
+                model.clone(),
+                circuit::SimMethod::Heuristic,
+            );
+            let probe_u = gadget.probes2vars.iter().position(|v| *v == 5).unwrap();
+            assert_eq!(
+                gadget.sim_probes(None, Some(probe_u)).len(),
+                n_inputs,
+                "reg: {}, model: {:?}",
+                reg,
+                model
+            );
+        }
+    }
+
+    #[test]
+    fn test_gate_model() {
+        let circ = refresh_sum(false);
+        let value = SimGadget::new(
+            circ.clone(),
+            |uc| 2 * uc - 1,
+            ProbeModel::Value,
+            circuit::SimMethod::Heuristic,
+        );
+        let gate = SimGadget::new(
+            circ,
+            |uc| 2 * uc - 1,
+            ProbeModel::Gate,
+            circuit::SimMethod::Heuristic,
+        );
+        // Gates t, s, u, o0 and o1, whatever their fanout.
+        assert_eq!(gate.n_pp(), 5);
+        assert_eq!(gate.pp_maxp(), &[1; 5]);
+        // u = a1 + (a0 + r) is uniform, but its operands reveal a1.
+        for (gadget, n_inputs) in [(value, 0), (gate, 1)] {
+            let probe_u = gadget.probes2vars.iter().position(|v| *v == 5).unwrap();
+            assert_eq!(gadget.sim_probes(None, Some(probe_u)).len(), n_inputs);
+        }
+    }
+
+    #[test]
+    fn test_coupling_model() {
+        let mut circ = refresh_sum(false);
+        // u is routed next to a0
+        circ.set_adjacency(&[(5, 0)]).unwrap();
+        assert!(circ.set_adjacency(&[(5, 8)]).is_err());
+        assert_eq!(circ.neighbours(0), &[5]);
+        assert_ne!(circ.fingerprint(), refresh_sum(false).fingerprint());
+        for (model, n_inputs) in [
+            (ProbeModel::Value, 0),
+            (ProbeModel::Coupling { glitches: false }, 1),
+            (ProbeModel::Coupling { glitches: true }, 2),
+        ] {
+            let gadget = SimGadget::new(
+                circ.clone(),
+                |uc| uc,
+                model.clone(),
+                circuit::SimMethod::Heuristic,
+            );
+            let probe_u = gadget.probes2vars.iter().position(|v| *v == 5).unwrap();
+            assert_eq!(
+                gadget.sim_probes(None, Some(probe_u)).len(),
+                n_inputs,
+                "model: {:?}",
+                model
+            );
+        }
+    }
+
+    #[test]
+    fn test_transition_model() {
+        // a0 + r and a1 + r computed in the same register
+        let vars = vec![
+            Var::new(VarSrc::Input(0, 0), None, "a0"),
+            Var::new(VarSrc::Input(0, 1), None, "a1"),
+            Var::new(VarSrc::Random, None, "r"),
+            Var::new(VarSrc::Sum(vec![0, 2]), Some((0, 0)), "t"),
+            Var::new(VarSrc::Sum(vec![1, 2]), Some((0, 1)), "u"),
+        ];
+        let circ = circuit::new_sl_sc(vars, 2, 1, 1).unwrap();
+        assert!(circ.transitions(&[(4, 0), (3, 0), (4, 1)]).is_err());
+        let prev = circ.transitions(&[(3, 0), (4, 0)]).unwrap();
+        assert_eq!(prev, vec![None, None, None, None, Some(3)]);
+        let gadget = SimGadget::new(
+            circ,
+            |uc| uc,
+            ProbeModel::Transition(prev),
+            circuit::SimMethod::Heuristic,
+        );
+        let probe_u = gadget.probes2vars.iter().position(|v| *v == 4).unwrap();
+        assert_eq!(gadget.sim_probes(None, Some(probe_u)).len(), 2);
+        assert_eq!(gadget.sim_probes(Some(1), None).len(), 0);
+    }
+
+    #[test]
+    fn test_const_public_not_probed() {
+        let vars = vec![
+            Var::new(VarSrc::Input(0, 0), None, "a0"),
+            Var::new(VarSrc::Input(0, 1), None, "a1"),
+            Var::new(VarSrc::Const(true), None, "one"),
+            Var::new(VarSrc::Public("k".to_owned()), None, "k"),
+            Var::new(VarSrc::Sum(vec![0, 2]), Some((0, 0)), "b0"),
+            Var::new(VarSrc::Sum(vec![1, 3]), Some((0, 1)), "b1"),
+        ];
+        let circ = circuit::new_sl_sc(vars, 2, 1, 1).unwrap();
+        let gadget = SimGadget::new(
+            circ,
+            |uc| uc,
+            ProbeModel::Value,
+            circuit::SimMethod::Heuristic,
+        );
+        assert!(!gadget.probes2vars.iter().any(|v| *v == 2 || *v == 3));
+    }
+}
@@ -21,8 +21,12 @@ mod gadget;
 pub(crate) mod multiprogress;
 mod pdt;
 mod rpm_sim;
+mod store;
 mod utils;
+mod word_gadget;
 
-pub(crate) use gadget::SimGadget;
+pub(crate) use gadget::{AnyGadget, ProbeModel, SimGadget};
 pub(crate) use pdt::ProbeDistribution;
 pub(crate) use rpm_sim::{CntSim, CntSimSt, GPdt, SampleRes, INPUT_AXIS};
+pub(crate) use store::{read_pdt, write_pdt, Checkpoint, Metadata};
+pub(crate) use word_gadget::WordGadget;
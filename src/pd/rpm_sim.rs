@@ -15,12 +15,14 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use super::combinatorics::{count_selections, MWCombinations};
+use super::gadget::N_PROBE_CLASSES;
 use indicatif::ProgressStyle;
 use itertools::Itertools;
 use ndarray::s;
 use ordered_float::OrderedFloat;
-use rand::Rng;
+use rand::{Rng, SeedableRng};
 use rayon::prelude::*;
+use std::collections::HashSet;
 use std::ops::AddAssign;
 
 pub const NPROBES_AXIS: ndarray::Axis = ndarray::Axis(0);
@@ -36,6 +38,8 @@ pub(crate) struct CntSim<Gadget: super::gadget::Gadget> {
     pp_sel_map: Vec<usize>,
     // minimum number of vars the can be touched by a gien number of probes
     n_probes_n_min_pp: Vec<usize>,
+    // indices in pp_sel_map of the probes of each class
+    class_sels: Vec<Vec<usize>>,
 }
 impl<Gadget: super::gadget::Gadget + Sync> CntSim<Gadget> {
     pub fn new(gadget: Gadget) -> Self {
@@ -62,6 +66,10 @@ impl<Gadget: super::gadget::Gadget + Sync> CntSim<Gadget> {
             .enumerate()
             .flat_map(|(i, x)| std::iter::repeat(i).take(*x as usize))
             .collect::<Vec<_>>();
+        let mut class_sels = vec![Vec::new(); N_PROBE_CLASSES];
+        for (i, pp) in pp_sel_map.iter().enumerate() {
+            class_sels[gadget.pp_class(*pp) as usize].push(i);
+        }
         return Self {
             gadget,
             max_nb_probes,
@@ -69,6 +77,7 @@ impl<Gadget: super::gadget::Gadget + Sync> CntSim<Gadget> {
             n_outputs,
             pp_sel_map,
             n_probes_n_min_pp,
+            class_sels,
         };
     }
     fn n_used_vars(&self) -> usize {
@@ -105,17 +114,27 @@ impl<Gadget: super::gadget::Gadget + Sync> CntSim<Gadget> {
         )
     }
 
-    fn gen_sel(&self, n_probes: usize) -> Vec<bool> {
+    /// Generator for sample number `sample` of the column (`output_id`, `n_probes`). It depends
+    /// only on its arguments, hence the counts do not depend on the scheduling of the samples.
+    fn sample_rng(seed: u64, output_id: usize, n_probes: usize, sample: u64) -> rand::rngs::StdRng {
+        let mut key = [0u8; 32];
+        let words = [seed, output_id as u64, n_probes as u64, sample];
+        for (chunk, x) in key.chunks_exact_mut(8).zip(words.iter()) {
+            chunk.copy_from_slice(&x.to_le_bytes());
+        }
+        rand::rngs::StdRng::from_seed(key)
+    }
+
+    fn gen_sel(&self, n_probes: usize, rng: &mut impl Rng) -> Vec<bool> {
         let n = self.pp_sel_map.len();
         if n_probes > n / 2 {
-            let mut res = self.gen_sel(n - n_probes);
+            let mut res = self.gen_sel(n - n_probes, rng);
             for x in res.iter_mut() {
                 *x = !*x;
             }
             return res;
         } else {
             let mut res = vec![false; n];
-            let mut rng = rand::thread_rng();
             // Robert Floyd's algorithm
             for j in (n - n_probes as usize)..n {
                 let r = rng.gen_range(0, j + 1);
@@ -240,13 +259,15 @@ impl<Gadget: super::gadget::Gadget + Sync> CntSim<Gadget> {
         )
     }
 
+    /// `gen_sel(i)` is the probe selection of sample number `first_sample + i`.
     fn probe_samples(
         &self,
         n_s_max: u32,
         suff_thresh: u32,
         n_sets: f64,
         output_id: usize,
-        n_probes: usize,
+        first_sample: u64,
+        gen_sel: &(impl Fn(u64) -> Vec<bool> + Sync),
     ) -> (Vec<u64>, u64) {
         firestorm::profile_fn!(probe_samples);
         let mut res = vec![0u64; 1 << self.n_inputs];
@@ -261,8 +282,8 @@ impl<Gadget: super::gadget::Gadget + Sync> CntSim<Gadget> {
             let tmp_res = range
                 .clone()
                 .into_par_iter()
-                .fold_with(vec![0u64; 1 << self.n_inputs], |mut tmp_res, _| {
-                    let probe_sel = self.gen_sel(n_probes);
+                .fold_with(vec![0u64; 1 << self.n_inputs], |mut tmp_res, i| {
+                    let probe_sel = gen_sel(first_sample + i as u64);
                     let input_offset = self.probe_set_pmask(output_id, &probe_sel);
                     tmp_res[input_offset] += 1;
                     tmp_res
@@ -279,6 +300,8 @@ impl<Gadget: super::gadget::Gadget + Sync> CntSim<Gadget> {
     }
 
     /// Return (Some(x), _) only when reaching the n_s_max upper bound.
+    /// `n_probes` is the column of the result, and `gen_sel(i)` is the probe selection of sample
+    /// number i.
     fn probe_auto_samples_inner(
         &self,
         n_s_max: u32,
@@ -286,17 +309,26 @@ impl<Gadget: super::gadget::Gadget + Sync> CntSim<Gadget> {
         n_sets: f64,
         output_id: usize,
         n_probes: usize,
+        gen_sel: &(impl Fn(u64) -> Vec<bool> + Sync),
     ) -> (Option<SampleRes>, u64) {
         firestorm::profile_fn!(probe_auto_samples_inner);
-        let (counts, cost1) = self.probe_samples(n_s_max, suff_thresh, n_sets, output_id, n_probes);
+        let (counts, cost1) =
+            self.probe_samples(n_s_max, suff_thresh, n_sets, output_id, 0, gen_sel);
         let tot_samples = counts.iter().copied().sum::<u64>() as u32;
         let (counts, cost2) = if tot_samples as f64 == n_sets {
             (None, 0)
         } else if tot_samples == n_s_max {
             (Some(counts), 0)
         } else {
-            let (counts, cost2) =
-                self.probe_samples(n_s_max, suff_thresh, n_sets, output_id, n_probes);
+            // Fresh samples, the first pass has less than n_s_max samples.
+            let (counts, cost2) = self.probe_samples(
+                n_s_max,
+                suff_thresh,
+                n_sets,
+                output_id,
+                n_s_max as u64,
+                gen_sel,
+            );
             (Some(counts), cost2)
         };
         let res = counts.map(|counts| SampleRes {
@@ -308,11 +340,15 @@ impl<Gadget: super::gadget::Gadget + Sync> CntSim<Gadget> {
         return (res, cost1 + cost2);
     }
 
+    /// Columns of the PDT for `output_id`, except the (output_id, n_probes) columns in `done`.
+    /// The random samples are derived from `seed`.
     pub(crate) fn probe_output<'a>(
         &'a self,
         output_id: usize,
         n_s_max: u32,
         suff_thresh: u32,
+        seed: u64,
+        done: &'a HashSet<(usize, usize)>,
         progress: &'a super::multiprogress::SubProgress,
     ) -> impl Iterator<Item = SampleRes> + 'a {
         firestorm::profile_fn!(probe_output);
@@ -346,6 +382,12 @@ impl<Gadget: super::gadget::Gadget + Sync> CntSim<Gadget> {
                 cost_exhaust,
                 output_id,
                 n_probes,
+                &|i| {
+                    self.gen_sel(
+                        n_probes,
+                        &mut Self::sample_rng(seed, output_id, n_probes, i),
+                    )
+                },
             );
             progress.inc_length(cost as i64);
             progress.inc(cost as i64);
@@ -399,33 +441,50 @@ impl<Gadget: super::gadget::Gadget + Sync> CntSim<Gadget> {
                 counts: counts.to_vec(),
                 exhaustive: true,
             };
-        // part 1.
-        let res1_vec = {
-            firestorm::profile_section!(res1);
-            self.probe_all_nprobes(output_id, 0..exh_low_ub, progress)
+        let is_done = move |n_probes: &usize| done.contains(&(output_id, *n_probes));
+        // Exhaustive sampling of the columns of `range` that are not done, `cost` is the progress
+        // length of the whole range.
+        let probe_all_todo = |range: std::ops::Range<usize>, cost: f64| {
+            let start = range.clone().find(|n| !is_done(n)).unwrap_or(range.end);
+            let end = range
+                .clone()
+                .rev()
+                .find(|n| !is_done(n))
+                .map_or(start, |n| n + 1);
+            let position = progress.position();
+            let counts = self.probe_all_nprobes(output_id, start..end, progress);
+            if (start..end) != range {
+                progress.inc_length(progress.position() - position - cost as i64);
+            }
+            counts
                 .axis_iter(ndarray::Axis(1))
                 .enumerate()
+                .map(|(i, counts)| (start + i, counts))
+                .filter(|(n_probes, _)| !is_done(n_probes))
                 .map(make_sample_res_exh)
                 .collect::<Vec<_>>()
         };
+        // part 1.
+        let res1_vec = {
+            firestorm::profile_section!(res1);
+            probe_all_todo(0..exh_low_ub, cost_low)
+        };
         // part 3.
         let res3_vec = {
             firestorm::profile_section!(res3);
-            self.probe_all_nprobes(
-                output_id,
-                exhaust_high_min_n_probes..self.n_nprobe_cases(),
-                progress,
-            )
-            .axis_iter(ndarray::Axis(1))
-            .enumerate()
-            .map(move |(i, counts)| make_sample_res_exh((exhaust_high_min_n_probes + i, counts)))
-            .collect::<Vec<_>>()
+            probe_all_todo(exhaust_high_min_n_probes..self.n_nprobe_cases(), cost_high)
         };
         // part 2.
         let res2 = {
             firestorm::profile_section!(res2);
             (exh_low_ub..exhaust_high_min_n_probes)
                 //.into_par_iter()
+                .filter(move |n_probes| {
+                    if is_done(n_probes) {
+                        progress.inc_length(-(n_s_max as i64));
+                    }
+                    !is_done(n_probes)
+                })
                 .map(move |n_probes| {
                     let (counts, cost) = self.probe_auto_samples_inner(
                         n_s_max,
@@ -433,6 +492,12 @@ impl<Gadget: super::gadget::Gadget + Sync> CntSim<Gadget> {
                         (n_s_max + 1) as f64,
                         output_id,
                         n_probes,
+                        &|i| {
+                            self.gen_sel(
+                                n_probes,
+                                &mut Self::sample_rng(seed, output_id, n_probes, i),
+                            )
+                        },
                     );
                     progress.inc_length(cost as i64 - n_s_max as i64);
                     progress.inc(cost as i64);
@@ -446,12 +511,16 @@ impl<Gadget: super::gadget::Gadget + Sync> CntSim<Gadget> {
             .chain(res2.into_iter())
     }
 
-    fn collect_pdtcols(&self, pdtcols: impl IntoIterator<Item = SampleRes>) -> CntSimSt {
+    fn collect_pdtcols(
+        &self,
+        class_sizes: Vec<u32>,
+        pdtcols: impl IntoIterator<Item = SampleRes>,
+    ) -> CntSimSt {
         firestorm::profile_fn!(collect_pdtcols);
-        let n_nprobes_cases = self.n_nprobe_cases();
         let n_input_cases = 1 << self.n_inputs;
         let n_output_cases = 1 << self.n_outputs;
-        let mut res = CntSimSt::new(n_nprobes_cases, n_input_cases, n_output_cases);
+        let mut res = CntSimSt::new(class_sizes, n_input_cases, n_output_cases);
+        let n_nprobes_cases = res.n_nprobes_cases();
         let mut init = ndarray::Array2::from_elem((n_output_cases, n_nprobes_cases), false);
         for pdtcol in pdtcols {
             assert_eq!(pdtcol.counts.len(), n_input_cases);
@@ -466,48 +535,298 @@ impl<Gadget: super::gadget::Gadget + Sync> CntSim<Gadget> {
         return res;
     }
 
-    pub fn run_sampling(&self, n_s_max: u32, suff_thresh: u32) -> CntSimSt {
+    /// The random samples are derived from `seed` (the result does not depend on the number of
+    /// threads), which is random by default. The finished columns are saved to `checkpoint`, and
+    /// the columns already in it are not computed again: resuming gives the same result as an
+    /// uninterrupted run. Without `seed`, the seed of the checkpoint is used, if any. The seed
+    /// used is stored in the result.
+    pub fn run_sampling(
+        &self,
+        n_s_max: u32,
+        suff_thresh: u32,
+        seed: Option<u64>,
+        mut checkpoint: Option<&mut super::Checkpoint>,
+    ) -> Result<CntSimSt, String> {
         firestorm::profile_fn!(run_sampling);
         assert!(n_s_max >= suff_thresh);
         let n_output_cases: usize = 1 << self.n_outputs;
+        let (seed, done) = match checkpoint.as_mut() {
+            Some(checkpoint) => {
+                let seed = checkpoint.start(n_s_max, suff_thresh, seed)?;
+                (
+                    seed,
+                    checkpoint.columns_done(
+                        1 << self.n_inputs,
+                        n_output_cases,
+                        self.n_nprobe_cases(),
+                    )?,
+                )
+            }
+            None => (seed.unwrap_or_else(rand::random), HashSet::new()),
+        };
+        let style = ProgressStyle::default_bar()
+            .template("{msg} [{bar:40}] {pos}/{len} [{elapsed_precise}>{eta_precise}]");
+        let progress = super::multiprogress::MultiProgressConfig::new(n_output_cases, style);
+        let mut pdtcols = progress.run(|mp| {
+            let mut pdtcols = Vec::new();
+            for output_id in 0..n_output_cases {
+                let local_progress = mp.sub(output_id);
+                for pdtcol in
+                    self.probe_output(output_id, n_s_max, suff_thresh, seed, &done, local_progress)
+                {
+                    match checkpoint.as_mut() {
+                        Some(checkpoint) => checkpoint.record(pdtcol)?,
+                        None => pdtcols.push(pdtcol),
+                    }
+                }
+            }
+            Ok::<_, String>(pdtcols)
+        })?;
+        if let Some(checkpoint) = checkpoint {
+            checkpoint.save()?;
+            pdtcols = checkpoint.columns().to_vec();
+        }
+        let mut res = self.collect_pdtcols(vec![self.max_nb_probes], pdtcols);
+        res.seed = Some(seed);
+        Ok(res)
+    }
+
+    /// Number of probes of each class (see `ProbeClass`).
+    pub fn class_sizes(&self) -> Vec<u32> {
+        self.class_sels
+            .iter()
+            .map(|sels| sels.len() as u32)
+            .collect()
+    }
+
+    fn gen_sel_classes(&self, n_probes: &[usize], rng: &mut impl Rng) -> Vec<bool> {
+        let mut res = vec![false; self.pp_sel_map.len()];
+        for (sels, n) in self.class_sels.iter().zip(n_probes) {
+            for i in rand::seq::index::sample(rng, sels.len(), *n).iter() {
+                res[sels[i]] = true;
+            }
+        }
+        res
+    }
+
+    /// Counts of the input sets for all the selections of `n_probes[c]` probes of each class c.
+    fn probe_all_classes(&self, output_id: usize, n_probes: &[usize]) -> Vec<u64> {
+        self.class_sels
+            .iter()
+            .zip(n_probes)
+            .map(|(sels, n)| sels.iter().copied().combinations(*n))
+            .multi_cartesian_product()
+            .collect::<Vec<_>>()
+            .into_par_iter()
+            .fold_with(vec![0u64; 1 << self.n_inputs], |mut res, sel| {
+                let pp = sel.iter().flatten().map(|i| self.pp_sel_map[*i]);
+                res[self.probe_set_pp(output_id, pp)] += 1;
+                res
+            })
+            .reduce(
+                || vec![0u64; 1 << self.n_inputs],
+                |mut r1, r2| {
+                    r1.iter_mut().zip(r2.iter()).for_each(|(x, y)| *x += *y);
+                    r1
+                },
+            )
+    }
+
+    /// Like `run_sampling`, but the first axis of the result is the (flattened) number of probes
+    /// of each class, see `CntSimSt`. Columns with at most `suff_thresh` probe selections are
+    /// computed exhaustively.
+    pub fn run_sampling_classes(&self, n_s_max: u32, suff_thresh: u32) -> CntSimSt {
+        firestorm::profile_fn!(run_sampling_classes);
+        assert!(n_s_max >= suff_thresh);
+        let class_sizes = self.class_sizes();
+        let n_cols = n_class_columns(&class_sizes).unwrap();
+        let n_output_cases: usize = 1 << self.n_outputs;
+        let seed = rand::random();
         let style = ProgressStyle::default_bar()
             .template("{msg} [{bar:40}] {pos}/{len} [{elapsed_precise}>{eta_precise}]");
         let progress = super::multiprogress::MultiProgressConfig::new(n_output_cases, style);
         progress.run(|mp| {
-            self.collect_pdtcols(
-                (0..n_output_cases)
-                    //.into_par_iter()
-                    .flat_map(|output_id| {
-                        let local_progress = mp.sub(output_id);
-                        self.probe_output(output_id, n_s_max, suff_thresh, local_progress)
+            let pdtcols = (0..n_output_cases)
+                .flat_map(|output_id| {
+                    let local_progress = mp.sub(output_id);
+                    local_progress.inc_length(n_cols as i64);
+                    local_progress.finishing(true);
+                    let class_sizes = &class_sizes;
+                    (0..n_cols).map(move |col| {
+                        let n_probes = class_counts(class_sizes, col);
+                        let n_sets = class_sizes
+                            .iter()
+                            .zip(n_probes.iter())
+                            .map(|(size, n)| {
+                                statrs::function::factorial::binomial(*size as u64, *n as u64)
+                            })
+                            .product::<f64>();
+                        let sampled = if n_sets <= suff_thresh as f64 {
+                            None
+                        } else {
+                            self.probe_auto_samples_inner(
+                                n_s_max,
+                                suff_thresh,
+                                n_sets,
+                                output_id,
+                                col,
+                                &|i| {
+                                    let mut rng = Self::sample_rng(seed, output_id, col, i);
+                                    self.gen_sel_classes(&n_probes, &mut rng)
+                                },
+                            )
+                            .0
+                        };
+                        local_progress.inc(1);
+                        sampled.unwrap_or_else(|| SampleRes {
+                            n_probes: col,
+                            output_index: output_id,
+                            counts: self.probe_all_classes(output_id, &n_probes),
+                            exhaustive: true,
+                        })
                     })
-                    .collect::<Vec<_>>()
-                    .into_iter(),
-            )
+                })
+                .collect::<Vec<_>>();
+            self.collect_pdtcols(class_sizes.clone(), pdtcols)
         })
     }
+
+    /// Whether the input shares `inputs` contain a full input sharing.
+    fn is_full_sharing(&self, inputs: &[usize]) -> bool {
+        let n_shares = self.gadget.n_shares();
+        (0..self.gadget.n_input_sharings())
+            .any(|port| (0..n_shares).all(|share| inputs.contains(&(port * n_shares + share))))
+    }
+
+    fn leaking_sets(&self, output_id: usize, max_size: usize) -> LeakingSets {
+        let mut min_sets = Vec::new();
+        let mut counts = Vec::new();
+        for size in 0..=std::cmp::min(max_size, self.n_used_vars()) {
+            let leaking = self
+                .iter_probe_set_min_weight(size, 0)
+                .collect::<Vec<_>>()
+                .into_par_iter()
+                .filter(|pp_set| {
+                    let inputs = self.gadget.sim_probes(
+                        super::utils::iter_set_bits(output_id),
+                        pp_set.iter().copied(),
+                    );
+                    self.is_full_sharing(&inputs)
+                })
+                .collect::<Vec<_>>();
+            counts.push(leaking.len() as u64);
+            if min_sets.is_empty() {
+                min_sets = leaking
+                    .into_iter()
+                    .map(|pp_set| {
+                        pp_set
+                            .into_iter()
+                            .map(|pp| self.gadget.pp_name(pp).to_owned())
+                            .collect()
+                    })
+                    .collect();
+            }
+        }
+        LeakingSets { min_sets, counts }
+    }
+
+    /// For each output subset (indexed as in `run_sampling`), the smallest sets of probed
+    /// variables whose simulation requires all the shares of an input, looking at sets of at most
+    /// `max_size` variables.
+    pub fn min_leaking_sets(&self, max_size: usize) -> Vec<LeakingSets> {
+        (0..(1 << self.n_outputs))
+            .map(|output_id| self.leaking_sets(output_id, max_size))
+            .collect()
+    }
+}
+
+/// Probe sets that require a full input sharing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LeakingSets {
+    /// Leaking sets of minimum size, as names of the probed variables (empty if no set of at
+    /// most the maximum size leaks).
+    pub min_sets: Vec<Vec<String>>,
+    /// Number of leaking sets of each size, from 0 to the maximum size.
+    pub counts: Vec<u64>,
+}
+
+/// Number of columns of a PDT with `class_sizes` probes in each class.
+fn n_class_columns(class_sizes: &[u32]) -> Result<usize, String> {
+    class_sizes
+        .iter()
+        .try_fold(1usize, |acc, n| acc.checked_mul(*n as usize + 1))
+        .ok_or_else(|| format!("too many PDT columns for class sizes {:?}", class_sizes))
+}
+
+/// Number of probes of each class in column `col` of a PDT: the columns are in row-major order
+/// of the per-class counts (the last class varies fastest).
+fn class_counts(class_sizes: &[u32], mut col: usize) -> Vec<usize> {
+    let mut res = vec![0; class_sizes.len()];
+    for (n, size) in res.iter_mut().zip(class_sizes.iter()).rev() {
+        *n = col % (*size as usize + 1);
+        col /= *size as usize + 1;
+    }
+    res
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub(crate) struct SampleRes {
+    /// Column in the PDT: number of probes, or flattened per-class numbers of probes.
     pub(crate) n_probes: usize,
     pub(crate) output_index: usize,
     pub(crate) counts: Vec<u64>,
     pub(crate) exhaustive: bool,
 }
 
+/// Counts of the input sets required for simulation, indexed by number of probes, input set and
+/// output set. With several probe classes, the number of probes is the flattened index of the
+/// numbers of probes of each class (see `class_counts`).
 #[derive(Debug, Clone)]
 pub struct CntSimSt {
     pub cnt: ndarray::Array3<u64>,
     pub exhaustive: ndarray::Array2<bool>,
+    /// Number of probes of each class.
+    pub class_sizes: Vec<u32>,
+    /// Seed of the sampling (see `CntSim::run_sampling`), if known.
+    pub seed: Option<u64>,
 }
 impl CntSimSt {
-    fn new(n_nprobes_cases: usize, n_input_cases: usize, n_output_cases: usize) -> Self {
+    fn new(class_sizes: Vec<u32>, n_input_cases: usize, n_output_cases: usize) -> Self {
+        let n_nprobes_cases = n_class_columns(&class_sizes).unwrap();
         Self {
             cnt: ndarray::Array3::zeros((n_nprobes_cases, n_input_cases, n_output_cases)),
             exhaustive: ndarray::Array2::from_elem((n_nprobes_cases, n_output_cases), false),
+            class_sizes,
+            seed: None,
         }
     }
+    /// `class_sizes` defaults to a single class.
+    pub fn from_arrays(
+        cnt: ndarray::Array3<u64>,
+        exhaustive: ndarray::Array2<bool>,
+        class_sizes: Option<Vec<u32>>,
+    ) -> Result<Self, String> {
+        let n_nprobes_cases = cnt.shape()[NPROBES_AXIS.index()];
+        if n_nprobes_cases == 0 {
+            return Err("no probe count in the counts array".to_owned());
+        }
+        let class_sizes = class_sizes.unwrap_or_else(|| vec![n_nprobes_cases as u32 - 1]);
+        if n_class_columns(&class_sizes)? != n_nprobes_cases {
+            return Err(format!(
+                "class sizes {:?} do not match {} probe counts",
+                class_sizes, n_nprobes_cases
+            ));
+        }
+        if exhaustive.shape() != [n_nprobes_cases, cnt.shape()[OUTPUT_AXIS.index()]] {
+            return Err("inconsistent shapes of counts and exhaustive arrays".to_owned());
+        }
+        Ok(Self {
+            cnt,
+            exhaustive,
+            class_sizes,
+            seed: None,
+        })
+    }
     fn n_nprobes_cases(&self) -> usize {
         self.cnt.shape()[NPROBES_AXIS.index()]
     }
@@ -522,7 +841,10 @@ impl CntSimSt {
         let ratios = ndarray::Array::from_shape_fn(self.cnt.raw_dim(), |(i, j, k)| {
             (self.cnt[(i, j, k)] as f64) / (tot[(i, k)] as f64)
         });
-        return GPdt { ratios };
+        return GPdt {
+            ratios,
+            class_sizes: self.class_sizes.clone(),
+        };
     }
     fn bound_margin(&self, err: f64) -> f64 {
         err / ((self.n_input_cases() * self.n_output_cases() * self.n_nprobes_cases()) as f64)
@@ -599,7 +921,10 @@ impl CntSimSt {
                         }
                     });
             });
-        return GPdt { ratios: bound };
+        return GPdt {
+            ratios: bound,
+            class_sizes: self.class_sizes.clone(),
+        };
     }
     pub fn ub(&self, err: f64, cum_tr: bool) -> GPdt {
         self.bound(err, true, cum_tr)
@@ -611,19 +936,52 @@ impl CntSimSt {
 
 pub struct GPdt {
     ratios: ndarray::Array3<f64>,
+    class_sizes: Vec<u32>,
 }
 impl GPdt {
+    pub(crate) fn new(ratios: ndarray::Array3<f64>, class_sizes: Vec<u32>) -> Result<Self, String> {
+        if n_class_columns(&class_sizes)? != ratios.shape()[NPROBES_AXIS.index()] {
+            return Err(format!(
+                "class sizes {:?} do not match {} probe counts",
+                class_sizes,
+                ratios.shape()[NPROBES_AXIS.index()]
+            ));
+        }
+        Ok(Self {
+            ratios,
+            class_sizes,
+        })
+    }
+    /// PDT where every probe leaks with probability `p`.
     pub fn instantiate(&self, p: f64) -> ndarray::Array2<f64> {
-        let n_max_probes = (self.ratios.shape()[0] - 1) as i32;
-        let coefs = (0..=n_max_probes)
-            .map(|i| {
-                p.powi(i)
-                    * (1.0 - p).powi(n_max_probes - i)
-                    // use statrs instead of num_integer as it gives directly a f64
-                    * statrs::function::factorial::binomial(n_max_probes as u64, i as u64)
+        self.instantiate_classes(&vec![p; self.class_sizes.len()])
+            .unwrap()
+    }
+    /// PDT where the probes of class c leak with probability `ps[c]`.
+    pub fn instantiate_classes(&self, ps: &[f64]) -> Result<ndarray::Array2<f64>, String> {
+        if ps.len() != self.class_sizes.len() {
+            return Err(format!(
+                "{} probabilities for {} probe classes",
+                ps.len(),
+                self.class_sizes.len()
+            ));
+        }
+        let coefs = (0..self.ratios.shape()[0])
+            .map(|col| {
+                class_counts(&self.class_sizes, col)
+                    .into_iter()
+                    .zip(self.class_sizes.iter())
+                    .zip(ps.iter())
+                    .map(|((i, n), p)| {
+                        p.powi(i as i32)
+                            * (1.0 - p).powi((*n as usize - i) as i32)
+                            // use statrs instead of num_integer as it gives directly a f64
+                            * statrs::function::factorial::binomial(*n as u64, i as u64)
+                    })
+                    .product()
             })
             .collect::<Vec<f64>>();
-        return ndarray::Array::from_shape_fn(
+        return Ok(ndarray::Array::from_shape_fn(
             (self.ratios.shape()[1], self.ratios.shape()[2]),
             |(i, j)| {
                 coefs
@@ -632,9 +990,84 @@ impl GPdt {
                     .map(|(k, c)| self.ratios[(k, i, j)] * c)
                     .sum()
             },
-        );
+        ));
+    }
+    pub fn class_sizes(&self) -> &[u32] {
+        &self.class_sizes
     }
     pub fn as_ratios(&self) -> &ndarray::Array3<f64> {
         &self.ratios
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::{build_isw, SimMethod};
+    use crate::pd::{ProbeModel, SimGadget};
+
+    #[test]
+    fn test_min_leaking_sets() {
+        let gadget = SimGadget::new(
+            build_isw(2),
+            |uc| uc,
+            ProbeModel::Value,
+            SimMethod::Heuristic,
+        );
+        let leaking = CntSim::new(gadget).min_leaking_sets(2);
+        assert_eq!(leaking.len(), 4);
+        // No output: two probes are needed, e.g. both shares of x.
+        assert_eq!(leaking[0].counts[..2], [0, 0]);
+        assert!(leaking[0]
+            .min_sets
+            .contains(&vec!["x_0".to_owned(), "x_1".to_owned()]));
+        // Both output shares reveal the output.
+        assert_eq!(leaking[3].counts[0], 1);
+        assert_eq!(leaking[3].min_sets, vec![Vec::<String>::new()]);
+    }
+
+    #[test]
+    fn test_classes() {
+        let gadget = SimGadget::new(
+            build_isw(2),
+            |uc| uc,
+            ProbeModel::Value,
+            SimMethod::Heuristic,
+        );
+        let cnt_sim = CntSim::new(gadget);
+        assert_eq!(cnt_sim.class_sizes(), vec![8, 2, 2, 4]);
+        // Everything is exhaustive.
+        let single = cnt_sim.run_sampling(1 << 20, 1 << 20, None, None).unwrap();
+        let classes = cnt_sim.run_sampling_classes(2000, 2000);
+        assert!(classes.exhaustive.iter().all(|x| *x));
+        let (single, classes) = (single.estimate(), classes.estimate());
+        let diff = &single.instantiate(0.1) - &classes.instantiate(0.1);
+        assert!(diff.iter().all(|x| x.abs() < 1e-12));
+        assert!(classes.instantiate_classes(&[0.1]).is_err());
+        let empty = CntSimSt::from_arrays(
+            ndarray::Array3::zeros((0, 16, 4)),
+            ndarray::Array2::from_elem((0, 4), true),
+            None,
+        );
+        assert!(empty.is_err());
+        let overflow = CntSimSt::from_arrays(
+            ndarray::Array3::zeros((1, 16, 4)),
+            ndarray::Array2::from_elem((1, 4), true),
+            Some(vec![u32::MAX; 4]),
+        );
+        assert!(overflow.is_err());
+        // Only the AND gates leak: a single product p_i_j reveals (x_i, y_j).
+        let pdt = classes.instantiate_classes(&[0.0, 0.0, 0.0, 0.5]).unwrap();
+        let p_none = pdt[(0, 0)];
+        assert!((p_none - 0.5f64.powi(4)).abs() < 1e-12);
+        let full_x = (0..16).filter(|i| i & 0b11 == 0b11).map(|i| pdt[(i, 0)]);
+        assert!(full_x.sum::<f64>() > 0.0);
+        let sampled = cnt_sim.run_sampling_classes(200, 100);
+        assert!(sampled.exhaustive.iter().any(|x| !*x));
+        assert!(sampled
+            .ub(1e-3, false)
+            .instantiate(0.1)
+            .iter()
+            .all(|x| *x >= 0.0));
+    }
+}
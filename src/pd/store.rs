@@ -0,0 +1,658 @@
+// STRAPS - Statistical Testing of RAndom Probing Security
+// Copyright (C) 2021 UCLouvain
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Binary file format for `CntSimSt`, `GPdt` and PDTs (`Array2<f64>`).
+//!
+//! All integers and floats are little-endian, a string is its length in bytes (u32) followed by
+//! its UTF-8 encoding.
+//! * magic `STRAPSPD` (8 bytes), format version (u32, currently 1), kind (u32: 0 for `CntSimSt`,
+//!   1 for `GPdt`, 2 for a PDT),
+//! * number of metadata entries (u32), then for each entry its key and its value (strings), see
+//!   [`Metadata`],
+//! * for `CntSimSt` and `GPdt`: number of probe classes (u32), then the size of each class (u32),
+//! * number of dimensions of the array (u32), then each dimension (u64), then the elements in
+//!   row-major order (u64 counts for `CntSimSt`, f64 otherwise),
+//! * for `CntSimSt`: the `exhaustive` array (shape: first and last dimensions of the counts), one
+//!   byte (0 or 1) per element.
+//!
+//! Checkpoints of `CntSim::run_sampling` (kind 3) contain, after the metadata, the
+//! number of finished columns (u64), then for each column its output index (u64), its number of
+//! probes (u64), whether it is exhaustive (one byte), the number of counts (u32) and the counts
+//! (u64).
+
+use super::gadget::N_PROBE_CLASSES;
+use super::rpm_sim::{CntSimSt, GPdt, SampleRes};
+use std::collections::HashSet;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+const MAGIC: &[u8; 8] = b"STRAPSPD";
+const FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    CntSimSt = 0,
+    GPdt = 1,
+    Pdt = 2,
+    Checkpoint = 3,
+}
+
+/// Context of a stored PDT. All fields are optional, except `straps_version` which is set when
+/// writing. Unknown keys are ignored when reading.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Metadata {
+    /// Fingerprint of the circuit (see `SlSharedCircuit::fingerprint`).
+    pub circuit: Option<String>,
+    pub n_shares: Option<u64>,
+    pub n_s_max: Option<u64>,
+    pub suff_thresh: Option<u64>,
+    pub use_copy: Option<bool>,
+    pub seed: Option<u64>,
+    /// Leakage model of the sampling (see `PyCompGraph.cnt_sim`).
+    pub glitches: Option<bool>,
+    /// Execution order of the transition leakage model, as (variable, time slot).
+    pub exec_order: Option<Vec<(u64, u64)>>,
+    pub gate_leakage: Option<bool>,
+    pub coupling: Option<bool>,
+    pub binarize: Option<bool>,
+    /// Simulatability algorithm (e.g. `heuristic`).
+    pub sim_method: Option<String>,
+    /// Version of STRAPS that wrote the file.
+    pub straps_version: Option<String>,
+}
+
+impl Metadata {
+    fn entries(&self) -> Vec<(&'static str, String)> {
+        let mut res = Vec::new();
+        let mut push = |key, value: Option<String>| {
+            if let Some(value) = value {
+                res.push((key, value));
+            }
+        };
+        push("circuit", self.circuit.clone());
+        push("n_shares", self.n_shares.map(|x| x.to_string()));
+        push("n_s_max", self.n_s_max.map(|x| x.to_string()));
+        push("suff_thresh", self.suff_thresh.map(|x| x.to_string()));
+        push("use_copy", self.use_copy.map(|x| x.to_string()));
+        push("seed", self.seed.map(|x| x.to_string()));
+        push("glitches", self.glitches.map(|x| x.to_string()));
+        push(
+            "exec_order",
+            self.exec_order.as_ref().map(|order| {
+                order
+                    .iter()
+                    .map(|(var, slot)| format!("{}:{}", var, slot))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            }),
+        );
+        push("gate_leakage", self.gate_leakage.map(|x| x.to_string()));
+        push("coupling", self.coupling.map(|x| x.to_string()));
+        push("binarize", self.binarize.map(|x| x.to_string()));
+        push("sim_method", self.sim_method.clone());
+        push("straps_version", Some(env!("CARGO_PKG_VERSION").to_owned()));
+        res
+    }
+
+    /// Settings of the sampling, which must not change when resuming it: all the entries but
+    /// the parameters of `CntSim::run_sampling` (checked by `Checkpoint::start`) and the STRAPS
+    /// version.
+    fn settings(&self) -> std::collections::BTreeMap<&'static str, String> {
+        let settings = Self {
+            n_s_max: None,
+            suff_thresh: None,
+            seed: None,
+            ..self.clone()
+        };
+        settings
+            .entries()
+            .into_iter()
+            .filter(|(key, _)| *key != "straps_version")
+            .collect()
+    }
+
+    fn set(&mut self, key: &str, value: String) -> Result<(), String> {
+        fn parse<T: std::str::FromStr>(key: &str, value: &str) -> Result<Option<T>, String> {
+            value
+                .parse()
+                .map(Some)
+                .map_err(|_| format!("invalid value '{}' for {}", value, key))
+        }
+        match key {
+            "circuit" => self.circuit = Some(value),
+            "n_shares" => self.n_shares = parse(key, &value)?,
+            "n_s_max" => self.n_s_max = parse(key, &value)?,
+            "suff_thresh" => self.suff_thresh = parse(key, &value)?,
+            "use_copy" => self.use_copy = parse(key, &value)?,
+            "seed" => self.seed = parse(key, &value)?,
+            "glitches" => self.glitches = parse(key, &value)?,
+            "exec_order" => {
+                self.exec_order = Some(
+                    value
+                        .split(',')
+                        .filter(|x| !x.is_empty())
+                        .map(|x| {
+                            let (var, slot) = x
+                                .split_once(':')
+                                .ok_or_else(|| format!("invalid value '{}' for {}", value, key))?;
+                            Ok((parse(key, var)?.unwrap(), parse(key, slot)?.unwrap()))
+                        })
+                        .collect::<Result<_, String>>()?,
+                )
+            }
+            "gate_leakage" => self.gate_leakage = parse(key, &value)?,
+            "coupling" => self.coupling = parse(key, &value)?,
+            "binarize" => self.binarize = parse(key, &value)?,
+            "sim_method" => self.sim_method = Some(value),
+            "straps_version" => self.straps_version = Some(value),
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+fn io_err(err: std::io::Error) -> String {
+    err.to_string()
+}
+
+fn write_u32(w: &mut impl Write, x: u32) -> Result<(), String> {
+    w.write_all(&x.to_le_bytes()).map_err(io_err)
+}
+
+fn write_str(w: &mut impl Write, s: &str) -> Result<(), String> {
+    write_u32(w, s.len() as u32)?;
+    w.write_all(s.as_bytes()).map_err(io_err)
+}
+
+fn read_bytes<const N: usize>(r: &mut impl Read) -> Result<[u8; N], String> {
+    let mut buf = [0; N];
+    r.read_exact(&mut buf).map_err(io_err)?;
+    Ok(buf)
+}
+
+fn read_u32(r: &mut impl Read) -> Result<u32, String> {
+    read_bytes(r).map(u32::from_le_bytes)
+}
+
+fn read_u64(r: &mut impl Read) -> Result<u64, String> {
+    read_bytes(r).map(u64::from_le_bytes)
+}
+
+fn read_str(r: &mut impl Read) -> Result<String, String> {
+    let len = read_u32(r)? as usize;
+    let mut buf = Vec::new();
+    r.take(len as u64).read_to_end(&mut buf).map_err(io_err)?;
+    if buf.len() != len {
+        return Err("unexpected end of file".to_owned());
+    }
+    String::from_utf8(buf).map_err(|_| "invalid UTF-8 string".to_owned())
+}
+
+fn write_header(w: &mut impl Write, kind: Kind, metadata: &Metadata) -> Result<(), String> {
+    w.write_all(MAGIC).map_err(io_err)?;
+    write_u32(w, FORMAT_VERSION)?;
+    write_u32(w, kind as u32)?;
+    let entries = metadata.entries();
+    write_u32(w, entries.len() as u32)?;
+    for (key, value) in entries {
+        write_str(w, key)?;
+        write_str(w, &value)?;
+    }
+    Ok(())
+}
+
+fn read_header(r: &mut impl Read, kind: Kind) -> Result<Metadata, String> {
+    if &read_bytes::<8>(r)? != MAGIC {
+        return Err("not a STRAPS PDT file".to_owned());
+    }
+    let version = read_u32(r)?;
+    if version != FORMAT_VERSION {
+        return Err(format!("unsupported format version {}", version));
+    }
+    let file_kind = read_u32(r)?;
+    if file_kind != kind as u32 {
+        return Err(format!(
+            "file contains object kind {}, expected {:?}",
+            file_kind, kind
+        ));
+    }
+    let mut metadata = Metadata::default();
+    for _ in 0..read_u32(r)? {
+        let key = read_str(r)?;
+        metadata.set(&key, read_str(r)?)?;
+    }
+    Ok(metadata)
+}
+
+fn write_class_sizes(w: &mut impl Write, class_sizes: &[u32]) -> Result<(), String> {
+    write_u32(w, class_sizes.len() as u32)?;
+    class_sizes.iter().try_for_each(|x| write_u32(w, *x))
+}
+
+fn read_class_sizes(r: &mut impl Read) -> Result<Vec<u32>, String> {
+    let n_classes = read_u32(r)?;
+    if n_classes as usize > N_PROBE_CLASSES {
+        return Err(format!("corrupt file: {} probe classes", n_classes));
+    }
+    (0..n_classes).map(|_| read_u32(r)).collect()
+}
+
+fn write_array<A, D>(
+    w: &mut impl Write,
+    array: &ndarray::Array<A, D>,
+    to_bytes: impl Fn(&A) -> [u8; 8],
+) -> Result<(), String>
+where
+    D: ndarray::Dimension,
+{
+    write_u32(w, array.ndim() as u32)?;
+    for dim in array.shape() {
+        w.write_all(&(*dim as u64).to_le_bytes()).map_err(io_err)?;
+    }
+    // Iteration is in logical (row-major) order, whatever the memory layout.
+    for x in array.iter() {
+        w.write_all(&to_bytes(x)).map_err(io_err)?;
+    }
+    Ok(())
+}
+
+fn read_array<A, D>(
+    r: &mut impl Read,
+    from_bytes: impl Fn([u8; 8]) -> A,
+) -> Result<ndarray::Array<A, D>, String>
+where
+    D: ndarray::Dimension,
+{
+    let ndim = read_u32(r)? as usize;
+    let shape = (0..ndim)
+        .map(|_| read_u64(r).map(|x| x as usize))
+        .collect::<Result<Vec<_>, _>>()?;
+    let len = shape
+        .iter()
+        .try_fold(1usize, |acc, x| acc.checked_mul(*x))
+        .ok_or_else(|| "corrupt file: array too large".to_owned())?;
+    let data = (0..len)
+        .map(|_| read_bytes(r).map(&from_bytes))
+        .collect::<Result<Vec<_>, _>>()?;
+    ndarray::Array::from_shape_vec(shape, data)
+        .map_err(|e| e.to_string())?
+        .into_dimensionality()
+        .map_err(|_| format!("expected a {}-dimensional array", D::NDIM.unwrap_or(0)))
+}
+
+impl CntSimSt {
+    pub fn write(&self, w: &mut impl Write, metadata: &Metadata) -> Result<(), String> {
+        write_header(w, Kind::CntSimSt, metadata)?;
+        write_class_sizes(w, &self.class_sizes)?;
+        write_array(w, &self.cnt, |x| x.to_le_bytes())?;
+        let exhaustive = self.exhaustive.iter().map(|x| *x as u8).collect::<Vec<_>>();
+        w.write_all(&exhaustive).map_err(io_err)
+    }
+
+    pub fn read(r: &mut impl Read) -> Result<(Self, Metadata), String> {
+        let metadata = read_header(r, Kind::CntSimSt)?;
+        let class_sizes = read_class_sizes(r)?;
+        let cnt: ndarray::Array3<u64> = read_array(r, u64::from_le_bytes)?;
+        let shape = (cnt.shape()[0], cnt.shape()[2]);
+        let mut exhaustive = vec![0; shape.0 * shape.1];
+        r.read_exact(&mut exhaustive).map_err(io_err)?;
+        let exhaustive = ndarray::Array2::from_shape_vec(shape, exhaustive)
+            .unwrap()
+            .mapv(|x| x != 0);
+        let mut st = CntSimSt::from_arrays(cnt, exhaustive, Some(class_sizes))?;
+        st.seed = metadata.seed;
+        Ok((st, metadata))
+    }
+}
+
+impl GPdt {
+    pub fn write(&self, w: &mut impl Write, metadata: &Metadata) -> Result<(), String> {
+        write_header(w, Kind::GPdt, metadata)?;
+        write_class_sizes(w, self.class_sizes())?;
+        write_array(w, self.as_ratios(), |x| x.to_le_bytes())
+    }
+
+    pub fn read(r: &mut impl Read) -> Result<(Self, Metadata), String> {
+        let metadata = read_header(r, Kind::GPdt)?;
+        let class_sizes = read_class_sizes(r)?;
+        let ratios = read_array(r, f64::from_le_bytes)?;
+        Ok((GPdt::new(ratios, class_sizes)?, metadata))
+    }
+}
+
+pub fn write_pdt(
+    w: &mut impl Write,
+    pdt: &ndarray::Array2<f64>,
+    metadata: &Metadata,
+) -> Result<(), String> {
+    write_header(w, Kind::Pdt, metadata)?;
+    write_array(w, pdt, |x| x.to_le_bytes())
+}
+
+pub fn read_pdt(r: &mut impl Read) -> Result<(ndarray::Array2<f64>, Metadata), String> {
+    let metadata = read_header(r, Kind::Pdt)?;
+    Ok((read_array(r, f64::from_le_bytes)?, metadata))
+}
+
+/// Finished columns of an interrupted `CntSim::run_sampling`, stored in a file.
+#[derive(Debug)]
+pub struct Checkpoint {
+    path: PathBuf,
+    /// Minimum time between two saves.
+    interval: Duration,
+    last_save: Instant,
+    metadata: Metadata,
+    columns: Vec<SampleRes>,
+}
+
+impl Checkpoint {
+    /// Checkpoint in `path` of the sampling with settings `context` (circuit fingerprint,
+    /// `use_copy`, leakage model...), resuming from its content if the file exists (and comes
+    /// from a sampling with the same settings).
+    pub fn open(
+        path: impl Into<PathBuf>,
+        interval: Duration,
+        context: &Metadata,
+    ) -> Result<Self, String> {
+        let path = path.into();
+        let (metadata, columns) = match std::fs::File::open(&path) {
+            Ok(f) => {
+                let (metadata, columns) = Self::read(&mut std::io::BufReader::new(f))
+                    .map_err(|e| format!("{}: {}", path.display(), e))?;
+                let (stored, expected) = (metadata.settings(), context.settings());
+                if let Some(key) = stored
+                    .keys()
+                    .chain(expected.keys())
+                    .find(|key| stored.get(*key) != expected.get(*key))
+                {
+                    return Err(format!(
+                        "{}: checkpoint has {} = {}, expected {}",
+                        path.display(),
+                        key,
+                        stored.get(key).map_or("none", |x| x.as_str()),
+                        expected.get(key).map_or("none", |x| x.as_str()),
+                    ));
+                }
+                (metadata, columns)
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => (context.clone(), vec![]),
+            Err(e) => return Err(format!("{}: {}", path.display(), e)),
+        };
+        Ok(Self {
+            path,
+            interval,
+            last_save: Instant::now(),
+            metadata,
+            columns,
+        })
+    }
+
+    fn read(r: &mut impl Read) -> Result<(Metadata, Vec<SampleRes>), String> {
+        let metadata = read_header(r, Kind::Checkpoint)?;
+        let columns = (0..read_u64(r)?)
+            .map(|_| {
+                let output_index = read_u64(r)? as usize;
+                let n_probes = read_u64(r)? as usize;
+                let exhaustive = read_bytes::<1>(r)?[0] != 0;
+                let counts = (0..read_u32(r)?)
+                    .map(|_| read_u64(r))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(SampleRes {
+                    n_probes,
+                    output_index,
+                    counts,
+                    exhaustive,
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok((metadata, columns))
+    }
+
+    fn write(&self, w: &mut impl Write) -> Result<(), String> {
+        write_header(w, Kind::Checkpoint, &self.metadata)?;
+        w.write_all(&(self.columns.len() as u64).to_le_bytes())
+            .map_err(io_err)?;
+        for col in self.columns.iter() {
+            w.write_all(&(col.output_index as u64).to_le_bytes())
+                .map_err(io_err)?;
+            w.write_all(&(col.n_probes as u64).to_le_bytes())
+                .map_err(io_err)?;
+            w.write_all(&[col.exhaustive as u8]).map_err(io_err)?;
+            write_u32(w, col.counts.len() as u32)?;
+            for x in col.counts.iter() {
+                w.write_all(&x.to_le_bytes()).map_err(io_err)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the checkpoint (through a temporary file, such that an interruption does not corrupt
+    /// it).
+    pub(crate) fn save(&mut self) -> Result<(), String> {
+        let mut tmp_path = self.path.clone().into_os_string();
+        tmp_path.push(".tmp");
+        let f = std::fs::File::create(&tmp_path).map_err(io_err)?;
+        let mut w = std::io::BufWriter::new(f);
+        self.write(&mut w)?;
+        w.into_inner()
+            .map_err(|e| e.to_string())?
+            .sync_all()
+            .map_err(io_err)?;
+        std::fs::rename(&tmp_path, &self.path).map_err(io_err)?;
+        self.last_save = Instant::now();
+        Ok(())
+    }
+
+    /// Check that the checkpoint comes from a run with the same parameters, and return the seed
+    /// to use (`seed`, or else the one of the checkpoint, or else a random one).
+    pub(crate) fn start(
+        &mut self,
+        n_s_max: u32,
+        suff_thresh: u32,
+        seed: Option<u64>,
+    ) -> Result<u64, String> {
+        let check = |name, stored: Option<u64>, value: u64| match stored {
+            Some(stored) if stored != value => Err(format!(
+                "checkpoint has {} = {}, expected {}",
+                name, stored, value
+            )),
+            _ => Ok(()),
+        };
+        let seed = seed.or(self.metadata.seed).unwrap_or_else(rand::random);
+        check("n_s_max", self.metadata.n_s_max, n_s_max as u64)?;
+        check("suff_thresh", self.metadata.suff_thresh, suff_thresh as u64)?;
+        check("seed", self.metadata.seed, seed)?;
+        self.metadata.n_s_max = Some(n_s_max as u64);
+        self.metadata.suff_thresh = Some(suff_thresh as u64);
+        self.metadata.seed = Some(seed);
+        Ok(seed)
+    }
+
+    /// The (output index, number of probes) of the finished columns.
+    pub(crate) fn columns_done(
+        &self,
+        n_input_cases: usize,
+        n_output_cases: usize,
+        n_nprobes_cases: usize,
+    ) -> Result<HashSet<(usize, usize)>, String> {
+        let mut done = HashSet::new();
+        for col in self.columns.iter() {
+            if col.counts.len() != n_input_cases
+                || col.output_index >= n_output_cases
+                || col.n_probes >= n_nprobes_cases
+            {
+                return Err("checkpoint does not match the gadget".to_owned());
+            }
+            if !done.insert((col.output_index, col.n_probes)) {
+                return Err("duplicate column in checkpoint".to_owned());
+            }
+        }
+        Ok(done)
+    }
+
+    /// Add a finished column, and save if the last save is older than the interval.
+    pub(crate) fn record(&mut self, column: SampleRes) -> Result<(), String> {
+        self.columns.push(column);
+        if self.last_save.elapsed() >= self.interval {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn columns(&self) -> &[SampleRes] {
+        &self.columns
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::{build_isw, SimMethod};
+    use crate::pd::{CntSim, ProbeModel, SimGadget};
+
+    #[test]
+    fn test_round_trip() {
+        let circ = build_isw(2);
+        let gadget = SimGadget::new(
+            circ.clone(),
+            |uc| uc,
+            ProbeModel::Value,
+            SimMethod::Heuristic,
+        );
+        let st = CntSim::new(gadget)
+            .run_sampling(100, 10, None, None)
+            .unwrap();
+        let metadata = Metadata {
+            circuit: Some(circ.fingerprint()),
+            n_shares: Some(2),
+            n_s_max: Some(100),
+            suff_thresh: Some(10),
+            use_copy: Some(false),
+            seed: None,
+            glitches: Some(true),
+            exec_order: Some(vec![(0, 1), (3, 0)]),
+            gate_leakage: Some(false),
+            coupling: Some(false),
+            binarize: Some(false),
+            sim_method: Some("heuristic".to_owned()),
+            straps_version: None,
+        };
+        let mut buf = Vec::new();
+        st.write(&mut buf, &metadata).unwrap();
+        let (st2, metadata2) = CntSimSt::read(&mut buf.as_slice()).unwrap();
+        assert_eq!(st2.cnt, st.cnt);
+        assert_eq!(st2.exhaustive, st.exhaustive);
+        assert_eq!(st2.class_sizes, st.class_sizes);
+        assert_eq!(
+            metadata2,
+            Metadata {
+                straps_version: Some(env!("CARGO_PKG_VERSION").to_owned()),
+                ..metadata.clone()
+            }
+        );
+        // Wrong kind and truncated file.
+        assert!(GPdt::read(&mut buf.as_slice()).is_err());
+        assert!(CntSimSt::read(&mut &buf[..buf.len() - 1]).is_err());
+        let gpdt = st.estimate();
+        let mut buf = Vec::new();
+        gpdt.write(&mut buf, &metadata).unwrap();
+        let (gpdt2, _) = GPdt::read(&mut buf.as_slice()).unwrap();
+        assert_eq!(gpdt2.as_ratios(), gpdt.as_ratios());
+        let pdt = gpdt.instantiate(0.01);
+        let mut buf = Vec::new();
+        write_pdt(&mut buf, &pdt, &Metadata::default()).unwrap();
+        assert_eq!(read_pdt(&mut buf.as_slice()).unwrap().0, pdt);
+        // Shape whose size overflows.
+        let mut buf = 2u32.to_le_bytes().to_vec();
+        buf.extend_from_slice(&u64::MAX.to_le_bytes());
+        buf.extend_from_slice(&2u64.to_le_bytes());
+        assert!(read_array::<f64, ndarray::Ix2>(&mut buf.as_slice(), f64::from_le_bytes).is_err());
+        // Too many probe classes.
+        let buf = 1000u32.to_le_bytes();
+        assert!(read_class_sizes(&mut buf.as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_checkpoint_resume() {
+        let gadget = SimGadget::new(
+            build_isw(3),
+            |uc| uc,
+            ProbeModel::Value,
+            SimMethod::Heuristic,
+        );
+        let cnt_sim = CntSim::new(gadget);
+        let context = Metadata {
+            circuit: Some(build_isw(3).fingerprint()),
+            use_copy: Some(false),
+            glitches: Some(false),
+            sim_method: Some("heuristic".to_owned()),
+            ..Default::default()
+        };
+        let path = std::env::temp_dir().join(format!("straps_checkpoint_{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let full = cnt_sim.run_sampling(100, 10, Some(42), None).unwrap();
+        assert!(full.exhaustive.iter().any(|x| !*x));
+        // Interrupted run: keep only some of the columns.
+        let mut checkpoint = Checkpoint::open(&path, Duration::from_secs(0), &context).unwrap();
+        cnt_sim
+            .run_sampling(100, 10, Some(42), Some(&mut checkpoint))
+            .unwrap();
+        let n_cols = checkpoint.columns.len();
+        checkpoint.columns.retain(|col| col.n_probes % 3 == 1);
+        assert!(checkpoint.columns.len() < n_cols);
+        checkpoint.save().unwrap();
+        // Checkpoint of another gadget or leakage model.
+        let others = [
+            Metadata {
+                circuit: Some(build_isw(2).fingerprint()),
+                ..context.clone()
+            },
+            Metadata {
+                use_copy: Some(true),
+                ..context.clone()
+            },
+            Metadata {
+                glitches: Some(true),
+                ..context.clone()
+            },
+            Metadata {
+                coupling: Some(false),
+                ..context.clone()
+            },
+        ];
+        for other in others.iter() {
+            assert!(Checkpoint::open(&path, Duration::from_secs(3600), other).is_err());
+        }
+        // The seed is taken from the checkpoint.
+        let mut checkpoint = Checkpoint::open(&path, Duration::from_secs(3600), &context).unwrap();
+        assert!(cnt_sim
+            .run_sampling(100, 11, None, Some(&mut checkpoint))
+            .is_err());
+        let resumed = cnt_sim
+            .run_sampling(100, 10, None, Some(&mut checkpoint))
+            .unwrap();
+        assert_eq!(resumed.cnt, full.cnt);
+        assert_eq!(resumed.exhaustive, full.exhaustive);
+        assert_eq!(resumed.seed, Some(42));
+        // Column with more probes than the gadget has.
+        checkpoint.columns[0].n_probes = 1000;
+        assert!(cnt_sim
+            .run_sampling(100, 10, None, Some(&mut checkpoint))
+            .is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
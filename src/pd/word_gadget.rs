@@ -0,0 +1,183 @@
+// STRAPS - Statistical Testing of RAndom Probing Security
+// Copyright (C) 2021 UCLouvain
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::gadget::{Gadget, ProbeClass};
+use crate::circuit;
+
+/// Gadget made of a word-level circuit, where each probe reveals the value of a wire.
+#[derive(Debug, Clone)]
+pub(crate) struct WordGadget {
+    circuit: circuit::WordCircuit,
+    max_probes: Vec<u32>,
+    probes2vars: Vec<usize>,
+    output_vars: Vec<usize>,
+    sim_method: circuit::WordSimMethod,
+}
+
+impl WordGadget {
+    pub(crate) fn new(
+        circuit: circuit::WordCircuit,
+        uc2maxp: impl Fn(u32) -> u32,
+        sim_method: circuit::WordSimMethod,
+    ) -> Result<Self, String> {
+        if sim_method == circuit::WordSimMethod::Exhaustive {
+            // Every probe set must be small enough.
+            let n_kind = |kind: fn(&circuit::WordSrc) -> bool| {
+                circuit.vars.iter().filter(|var| kind(&var.src)).count()
+            };
+            circuit.exhaustive_size(
+                n_kind(|src| matches!(src, circuit::WordSrc::Input(_, _))),
+                n_kind(|src| *src == circuit::WordSrc::Random),
+            )?;
+        }
+        let mut sorted_probe_vars = circuit
+            .var_use_counts()
+            .into_iter()
+            .enumerate()
+            .filter(|(v, use_count)| {
+                *use_count != 0 && !matches!(circuit.vars[*v].src, circuit::WordSrc::Const(_))
+            })
+            .collect::<Vec<_>>();
+        sorted_probe_vars.sort_unstable_by(|(_, uc1), (_, uc2)| uc2.cmp(uc1));
+        let mut output_vars = circuit
+            .vars
+            .iter()
+            .enumerate()
+            .filter_map(|(i, var)| var.output_port.map(|port| (port, i)))
+            .collect::<Vec<_>>();
+        output_vars.sort_unstable();
+        Ok(Self {
+            max_probes: sorted_probe_vars
+                .iter()
+                .map(|(_, uc)| uc2maxp(*uc))
+                .collect(),
+            probes2vars: sorted_probe_vars.into_iter().map(|(v, _)| v).collect(),
+            output_vars: output_vars.into_iter().map(|(_, i)| i).collect(),
+            circuit,
+            sim_method,
+        })
+    }
+}
+
+impl Gadget for WordGadget {
+    fn n_outputs(&self) -> usize {
+        self.n_output_sharings() * self.n_shares()
+    }
+    fn n_inputs(&self) -> usize {
+        self.n_input_sharings() * self.n_shares()
+    }
+    fn max_n_probes(&self) -> u32 {
+        self.max_probes.iter().copied().sum::<u32>()
+    }
+    fn n_shares(&self) -> usize {
+        self.circuit.n_shares
+    }
+    fn n_input_sharings(&self) -> usize {
+        self.circuit.n_input_ports()
+    }
+    fn n_output_sharings(&self) -> usize {
+        self.circuit.n_output_ports()
+    }
+    fn n_pp(&self) -> usize {
+        self.max_probes.len()
+    }
+    fn pp_maxp(&self) -> &[u32] {
+        &self.max_probes
+    }
+    fn pp_name(&self, pp: usize) -> &str {
+        &self.circuit.vars[self.probes2vars[pp]].name
+    }
+    fn pp_class(&self, pp: usize) -> ProbeClass {
+        use circuit::WordSrc;
+        match self.circuit.vars[self.probes2vars[pp]].src {
+            WordSrc::Input(_, _) => ProbeClass::Input,
+            WordSrc::Random => ProbeClass::Random,
+            WordSrc::MulMod(_) | WordSrc::And(_) => ProbeClass::NonLinear,
+            WordSrc::FieldSquare(_) if !matches!(self.circuit.ring, circuit::Ring::Gf2n { .. }) => {
+                ProbeClass::NonLinear
+            }
+            WordSrc::AddMod(_)
+            | WordSrc::SubMod(_)
+            | WordSrc::Xor(_)
+            | WordSrc::MulConst(_, _)
+            | WordSrc::FieldSquare(_)
+            | WordSrc::Linear(_, _)
+            | WordSrc::Affine(_, _, _) => ProbeClass::Linear,
+            WordSrc::Const(_) => unreachable!(),
+        }
+    }
+    fn sim_probes(
+        &self,
+        outputs: impl IntoIterator<Item = usize>,
+        probes: impl IntoIterator<Item = usize>,
+    ) -> Vec<usize> {
+        let mut vars = outputs
+            .into_iter()
+            .map(|o| self.output_vars[o])
+            .chain(probes.into_iter().map(|p| self.probes2vars[p]))
+            .collect::<Vec<_>>();
+        vars.sort_unstable();
+        vars.dedup();
+        // The size of the exhaustive enumeration is checked in `new`.
+        self.circuit
+            .sim_set(&vars, self.sim_method)
+            .unwrap()
+            .into_iter()
+            .map(|i| match self.circuit.vars[i].src {
+                circuit::WordSrc::Input(port, share) => port * self.n_shares() + share,
+                _ => unreachable!(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::{Ring, Sharing, WordSimMethod, WordSrc, WordVar};
+    use crate::pd::CntSim;
+
+    #[test]
+    fn test_arithmetic_refresh() {
+        // (a0 + r, a1 - r)
+        let vars = vec![
+            WordVar::new(WordSrc::Input(0, 0), None, "a0"),
+            WordVar::new(WordSrc::Input(0, 1), None, "a1"),
+            WordVar::new(WordSrc::Random, None, "r"),
+            WordVar::new(WordSrc::AddMod([0, 2]), Some((0, 0)), "b0"),
+            WordVar::new(WordSrc::SubMod([1, 2]), Some((0, 1)), "b1"),
+        ];
+        let circ = circuit::WordCircuit::new(
+            Ring::Mod2k(4),
+            vars,
+            2,
+            vec![Sharing::Arithmetic],
+            vec![Sharing::Arithmetic],
+        )
+        .unwrap();
+        for method in [WordSimMethod::Heuristic, WordSimMethod::Exhaustive] {
+            let gadget = WordGadget::new(circ.clone(), |uc| uc, method).unwrap();
+            assert_eq!(gadget.n_pp(), 3);
+            let leaking = CntSim::new(gadget).min_leaking_sets(2);
+            assert_eq!(leaking[0].counts[..2], [0, 0]);
+            assert_eq!(
+                leaking[0].min_sets,
+                vec![vec!["a0".to_owned(), "a1".to_owned()]]
+            );
+            assert_eq!(leaking[3].counts[0], 1);
+        }
+    }
+}
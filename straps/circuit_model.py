@@ -27,8 +27,15 @@ KIND_MAP = {
     "sum": PyCompGraph.VAR_KIND_SUM,
     "product": PyCompGraph.VAR_KIND_PRODUCT,
     "not": PyCompGraph.VAR_KIND_NOT,
+    "reg": PyCompGraph.VAR_KIND_REG,
+    "zero": PyCompGraph.VAR_KIND_ZERO,
+    "one": PyCompGraph.VAR_KIND_ONE,
+    "public": PyCompGraph.VAR_KIND_PUBLIC,
 }
 
+# Kinds of variables that have no operands.
+SOURCE_KINDS = ("input", "random", "zero", "one", "public")
+
 
 class Circuit:
     def __init__(self, d=None):
@@ -38,6 +45,7 @@ class Circuit:
         self.l_sums = []
         self.l_prods = []
         self.ngates = []
+        self.regs = []
         self.input_ports = {}
         self.output_ports = {}
 
@@ -72,6 +80,11 @@ class Circuit:
         self.check_params_op(dest, (op,))
         self.ngates.append((dest, (op,)))
 
+    def reg(self, dest, op):
+        """Register: stops glitch propagation in the robust probing model."""
+        self.check_params_op(dest, (op,))
+        self.regs.append((dest, (op,)))
+
     def l_sum(self, dest, ops):
         self.check_params_op(dest, ops)
         self.l_sums.append((dest, ops))
@@ -112,8 +125,12 @@ class Circuit:
             key=lambda iv: self.input_ports[iv[1].idx],
         ):
             copy_var(i)
-        copy_all_satisfying_predicate(lambda _, v: v.kind == "random")
-        ops_to_analyze = self.l_sums + self.l_prods + self.ngates + self.assigns
+        copy_all_satisfying_predicate(
+            lambda _, v: v.kind in SOURCE_KINDS and v.kind != "input"
+        )
+        ops_to_analyze = (
+            self.l_sums + self.l_prods + self.ngates + self.regs + self.assigns
+        )
         while ops_to_analyze:
             new_ops_to_analyze = []
             for dest, ops in ops_to_analyze:
@@ -146,6 +163,7 @@ class Circuit:
                     for dest, _ in self.l_sums
                     + self.l_prods
                     + self.ngates
+                    + self.regs
                     + self.assigns
                     if dest.idx == v.idx
                 ], "{}".format(v)
@@ -163,6 +181,12 @@ class Circuit:
                 )
             elif any(self.vars[ov].kind == "random" for ov in olds):
                 kind = "random"
+            elif any(self.vars[ov].kind in SOURCE_KINDS for ov in olds):
+                kind = next(
+                    self.vars[ov].kind
+                    for ov in olds
+                    if self.vars[ov].kind in SOURCE_KINDS
+                )
             elif any(self.vars[ov].kind == "intermediate" for ov in olds):
                 kind = "intermediate"
             else:
@@ -173,7 +197,7 @@ class Circuit:
             new_c.var(
                 self.vars[next(iter(olds))].name, continuous, kind, in_port, out_port
             )
-        copied_attrs = ["l_sums", "l_prods", "ngates"]
+        copied_attrs = ["l_sums", "l_prods", "ngates", "regs"]
         if copy_assigns:
             copied_attrs.append("assigns")
         for attr in copied_attrs:
@@ -199,20 +223,20 @@ class Circuit:
         sc, _ = sc.sorted_vars()
         comp_graph = [None for _ in sc.vars]
         sum_ops = [
-            (dest, KIND_MAP["sum"], [op1.idx, op2.idx])
-            for dest, (op1, op2) in sc.l_sums
+            (dest, KIND_MAP["sum"], [op.idx for op in ops]) for dest, ops in sc.l_sums
         ]
         prod_ops = [
-            (dest, KIND_MAP["product"], [op1.idx, op2.idx])
-            for dest, (op1, op2) in sc.l_prods
+            (dest, KIND_MAP["product"], [op.idx for op in ops])
+            for dest, ops in sc.l_prods
         ]
         not_ops = [(dest, KIND_MAP["not"], [op1.idx]) for dest, (op1,) in sc.ngates]
-        for dest, op_kind, opsidx in sum_ops + prod_ops + not_ops:
+        reg_ops = [(dest, KIND_MAP["reg"], [op1.idx]) for dest, (op1,) in sc.regs]
+        for dest, op_kind, opsidx in sum_ops + prod_ops + not_ops + reg_ops:
             assert comp_graph[dest.idx] is None
             assert dest.kind == "intermediate"
             comp_graph[dest.idx] = (op_kind, opsidx, dest.name)
         for var in sc.vars:
-            if var.kind in ("random", "input"):
+            if var.kind in SOURCE_KINDS:
                 assert comp_graph[var.idx] is None
                 comp_graph[var.idx] = (KIND_MAP[var.kind], [], var.name)
         for i, x in enumerate(comp_graph):
@@ -27,8 +27,14 @@ from . import utils
 ## Generic circuit builders
 
 
-def eval_aes_sbox(p, pdts, ref=True, d=2, ref_name="optref"):
-    """AES S-box in GF(256). See paper for structure."""
+def eval_aes_sbox(
+    p, pdts, ref=True, d=2, ref_name="optref", mul="ISW", square="square"
+):
+    """AES S-box in GF(256). See paper for structure.
+
+    `mul` and `square` are the gadgets used for multiplications and squares,
+    e.g. "ISW_gf256" and "square_gf256" for the GF(2^8) word-level gadgets.
+    """
     # Create the Shared PD with one output sharing
     x = ShPd(["out"], d)
     # We build the circuit from the output: we start from the output sharing,
@@ -37,15 +43,15 @@ def eval_aes_sbox(p, pdts, ref=True, d=2, ref_name="optref"):
 
     # Apply the output multiplication of the AES S-box
     # out = m3i1 * m3i2 (with ISW multiplication)
-    x.op("out", ["m3i1", "m3i2"], pdts["ISW"])
+    x.op("out", ["m3i1", "m3i2"], pdts[mul])
     # Another multiplication
     # m3i1 = m2i1 * m2i2
-    x.op("m3i1", ["m2i1", "m2i2"], pdts["ISW"])
+    x.op("m3i1", ["m2i1", "m2i2"], pdts[mul])
     # Let m2i2 <- m2i2**(2**4) (we can override variables)
     for _ in range(4):
-        x.op("m2i2", ["m2i2"], pdts["square"])
+        x.op("m2i2", ["m2i2"], pdts[square])
     # m2i2 = m1i1 * m1i2
-    x.op("m2i2", ["m1i1", "m1i2"], pdts["ISW"])
+    x.op("m2i2", ["m1i1", "m1i2"], pdts[mul])
     # Copy gate: m2i1 <- t0 and m1i1 <- t0.
     x.split_sharing("t0", "m2i1", "m1i1")
     # Refresh: t0 <- Refresh(t0)
@@ -53,18 +59,18 @@ def eval_aes_sbox(p, pdts, ref=True, d=2, ref_name="optref"):
         x.op("t0", ["t0"], pdts[ref_name])
     # t0 <- t0**(2**2)
     for _ in range(2):
-        x.op("t0", ["t0"], pdts["square"])
+        x.op("t0", ["t0"], pdts[square])
     # Copy gate: t0 <- m0o and m1i2 <- m0o
     x.split_sharing("m0o", "t0", "m1i2")
     # m0o = m0i1 * m0i2
-    x.op("m0o", ["m0i1", "m0i2"], pdts["ISW"])
+    x.op("m0o", ["m0i1", "m0i2"], pdts[mul])
     # Copy gate: m0i2 <- t1 and m3i2 <- t1
     x.split_sharing("t1", "m0i2", "m3i2")
     # Refresh: t1 <- Refresh(t1)
     if ref:
         x.op("t1", ["t1"], pdts[ref_name])
     # t1 <- t1**2
-    x.op("t1", ["t1"], pdts["square"])
+    x.op("t1", ["t1"], pdts[square])
     # Copy gate: m0i1 <- in and t1 <- in
     x.split_sharing("in", "m0i1", "t1")
     return x.security("in")
@@ -99,7 +105,12 @@ def eval_n_sharings(p, pdts, d=2, circ_name=None, n_inputs=1, sec_input=0):
 base_circuits = {
     "eval_aes_sbox": (
         eval_aes_sbox,
-        lambda **kwargs: ["ISW", "simpleref", "optref", "square"],
+        lambda mul="ISW", square="square", **kwargs: [
+            mul,
+            "simpleref",
+            "optref",
+            square,
+        ],
     ),
     "eval_x_cube": (
         eval_x_cube,
@@ -115,6 +126,11 @@ base_circuits = {
 specialized_circuits = {
     "aes_sbox": ("AES S-box", "eval_aes_sbox", {"ref_name": "optref"}),
     "aes_sbox_noref": ("AES S-box no refresh", "eval_aes_sbox", {"ref": False}),
+    "aes_sbox_gf256": (
+        "AES S-box GF(2^8) gadgets",
+        "eval_aes_sbox",
+        {"ref_name": "optref", "mul": "ISW_gf256", "square": "square_gf256"},
+    ),
     "aes_sbox_simpleref": (
         "AES S-box simple refresh",
         "eval_aes_sbox",
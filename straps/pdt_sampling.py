@@ -23,8 +23,8 @@ from . import simple_circuits
 from . import utils
 
 # We serialize to have simple way to store to disk for caching.
-# The proper way would be to implement the proper serialization/deserialization
-# on the rust objects, but this is currently not supported by pyo3.
+# To share results, prefer `save_cnt_pdt`, which uses the STRAPS binary format
+# and records the context of the sampling.
 @utils.pdt_cache.cache
 def serialized_cnt_pdt_raw(circ_name, d, n_s_max, suff_thresh, use_copy):
     print(
@@ -40,6 +40,7 @@ def serialized_cnt_pdt_raw(circ_name, d, n_s_max, suff_thresh, use_copy):
     return {
         "pdt_sampling": pcntsimst.to_array(),
         "exhaustive": pcntsimst.exhaustive(),
+        "seed": pcntsimst.seed(),
     }
 
 
@@ -49,6 +50,36 @@ def cnt_pdt_raw(circ_name, d, n_s_max, suff_thresh, use_copy):
     return res
 
 
+def save_cnt_pdt(
+    path, circ_name, d, n_s_max, suff_thresh, use_copy, seed=None, checkpoint=None
+):
+    """Store the sampling result in path, with its metadata (see
+    PyCntSimSt.load). With a seed or a checkpoint file, the sampling is run
+    (or resumed from the checkpoint) without the cache."""
+    pcg = simple_circuits.circ2pcg(circ_name, d)
+    pcntsim = pcg.cnt_sim(use_copy)
+    if seed is None and checkpoint is None:
+        res = cnt_pdt_raw(circ_name, d, n_s_max, suff_thresh, use_copy)
+        # Results cached by older versions have no seed.
+        pcntsimst, seed = res["pcntsim"], res.get("seed")
+    else:
+        pcntsimst = utils.interruptible(
+            pcntsim.run_sampling,
+            n_s_max,
+            suff_thresh,
+            seed=seed,
+            checkpoint=checkpoint,
+        )
+        seed = pcntsimst.seed()
+    pcntsimst.save(
+        path,
+        n_s_max=n_s_max,
+        suff_thresh=suff_thresh,
+        seed=seed,
+        **pcntsim.metadata(),
+    )
+
+
 # We don't bother using a disk cache for this, as it is reasonnably fast to
 # compute, but an in-memory cache doesn't hurt.
 @ft.lru_cache(maxsize=None)
@@ -75,11 +106,18 @@ if __name__ == "__main__":
     parser.add_argument("--nsmax", default=10 ** 3)
     parser.add_argument("--suffthresh", default=10 ** 3)
     parser.add_argument("--usecopy", default=1)
+    parser.add_argument("--output", help="Store the result in STRAPS format.")
+    parser.add_argument("--seed", type=int)
+    parser.add_argument("--checkpoint", help="Checkpoint file (to resume long runs).")
     args = parser.parse_args()
-    serialized_cnt_pdt_raw(
+    params = (
         args.circname,
         int(args.nshares),
         int(args.nsmax),
         int(args.suffthresh),
         bool(int(args.usecopy)),
     )
+    if args.output:
+        save_cnt_pdt(args.output, *params, seed=args.seed, checkpoint=args.checkpoint)
+    else:
+        serialized_cnt_pdt_raw(*params)
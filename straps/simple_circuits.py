@@ -18,6 +18,7 @@
 """Base circuits for which we compute the PDTs."""
 
 from . import circuit_model
+from ._straps_ext import PyWordCircuit
 
 
 def op_preamble(d, n_inputs):
@@ -290,12 +291,21 @@ all_circs = {
 }
 
 
+# map 'name' -> builder(d) for word-level gadgets (built directly as PyWordCircuit)
+word_circs = {
+    "ISW_gf256": lambda d: PyWordCircuit.isw("gf(2^8)", d),
+    "square_gf256": lambda d: PyWordCircuit.square("gf(2^8)", d),
+}
+
+
 def circ2pcg(name, d):
+    if name in word_circs:
+        return word_circs[name](d)
     return all_circs[name](d).to_comp_graph()
 
 
 if __name__ == "__main__":
-    for k in all_circs.keys():
+    for k in list(all_circs.keys()) + list(word_circs.keys()):
         print("testing", k)
         for d in range(1, 7):
             circ2pcg(k, d)